@@ -1,16 +1,24 @@
 use crate::{
-    render::{Render, RenderFactory, RenderProps},
-    windows::{alerts::*, menu::Menu},
+    events::{enter_component, RedrawHandle},
+    hooks::TimerId,
+    render::{EventContext, EventResult, LayoutEventHandler, Render, RenderFactory, RenderProps},
+    windows::{alerts::*, focus::FocusHandle, menu::Menu},
 };
 use ratatui::{
     buffer::Buffer,
-    layout::{Constraint, Direction, Position, Rect},
+    layout::{Constraint, Direction, Layout, Margin, Position, Rect},
+    widgets::Padding,
 };
 use std::{
     any::Any,
     cell::{RefCell, RefMut},
-    collections::HashMap,
-    ops::{Deref, DerefMut},
+    collections::{HashMap, HashSet},
+    ops::{BitOr, BitOrAssign, Deref, DerefMut},
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
 };
 
 #[derive(Debug, Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -28,17 +36,125 @@ impl Default for RenderId {
     }
 }
 
+/// Constraint used for layout children that don't specify one.
+pub const DEFAULT_CONSTRAINT: Constraint = Constraint::Fill(1);
+
+/// What may follow a widget in `column_widget!` and `row_widget!`: a
+/// `Constraint`, a key, or both as a `(Constraint, key)` tuple.
+pub struct ChildOptions {
+    pub constraint: Constraint,
+    pub key: Option<String>,
+}
+
+impl ChildOptions {
+    #[doc(hidden)]
+    pub fn apply(self, component: RenderComponent) -> (Constraint, RenderComponent) {
+        let component = match self.key {
+            Some(key) => component.with_key(key),
+            None => component,
+        };
+        (self.constraint, component)
+    }
+}
+
+impl From<Constraint> for ChildOptions {
+    fn from(constraint: Constraint) -> Self {
+        Self {
+            constraint,
+            key: None,
+        }
+    }
+}
+
+impl From<&str> for ChildOptions {
+    fn from(key: &str) -> Self {
+        Self {
+            constraint: DEFAULT_CONSTRAINT,
+            key: Some(key.into()),
+        }
+    }
+}
+
+impl From<String> for ChildOptions {
+    fn from(key: String) -> Self {
+        Self {
+            constraint: DEFAULT_CONSTRAINT,
+            key: Some(key),
+        }
+    }
+}
+
+impl<S: Into<String>> From<(Constraint, S)> for ChildOptions {
+    fn from((constraint, key): (Constraint, S)) -> Self {
+        Self {
+            constraint,
+            key: Some(key.into()),
+        }
+    }
+}
+
 #[derive(PartialEq, Eq)]
 pub enum LayoutDirection {
     Column,
     Row,
 }
 
+impl From<&LayoutDirection> for Direction {
+    fn from(value: &LayoutDirection) -> Self {
+        match value {
+            LayoutDirection::Column => Direction::Vertical,
+            LayoutDirection::Row => Direction::Horizontal,
+        }
+    }
+}
+
 /// Wrapper for factory of components.
 /// We use interior mutability to cache the results of the render factory.
 pub struct RenderFactoryBox {
     cached: RefCell<Option<RenderComponent>>,
     render: RefCell<Box<dyn RenderFactory>>,
+    state: Arc<FactoryState>,
+    /// Given to the root of every tree built, see `RenderComponent::with_key`
+    key: Option<String>,
+}
+
+thread_local! {
+    static CURRENT_FACTORY: RefCell<Option<FactoryHandle>> = const { RefCell::new(None) };
+}
+
+#[derive(Default)]
+struct FactoryState {
+    invalidated: AtomicBool,
+    redraw: Mutex<Option<RedrawHandle>>,
+}
+
+/// Makes a factory build its components again before the next frame. It can be
+/// cloned and sent to other threads.
+#[derive(Clone)]
+pub struct FactoryHandle {
+    state: Arc<FactoryState>,
+}
+
+impl FactoryHandle {
+    /// The handle of the factory currently building its components on this
+    /// thread, for factories to keep
+    pub fn current() -> Option<FactoryHandle> {
+        CURRENT_FACTORY.with(|current| current.borrow().clone())
+    }
+
+    /// Drops the components built by the factory, asking the window to redraw
+    pub fn invalidate(&self) {
+        self.state.invalidated.store(true, Ordering::SeqCst);
+        if let Ok(redraw) = self.state.redraw.lock() {
+            if let Some(redraw) = redraw.as_ref() {
+                redraw.request_redraw()
+            }
+        }
+    }
+
+    pub fn is_invalidated(&self) -> bool {
+        self.state.invalidated.load(Ordering::SeqCst)
+    }
 }
 
 struct RW<'a> {
@@ -71,9 +187,123 @@ impl<'b> DerefMut for RWMut<'b> {
     }
 }
 
+/// What `RenderComponent::refresh_factories` did
+pub(crate) struct FactoryRefresh {
+    /// Whether the tree changed
+    pub(crate) changed: bool,
+    /// The key that a rejected rebuild would have used twice
+    pub(crate) duplicate_key: Option<String>,
+}
+
+/// How many times each key is used in a tree
+#[derive(Default)]
+struct KeyCounts {
+    counts: HashMap<String, usize>,
+    /// The last key that a factory rebuild would have used twice
+    rejected: Option<String>,
+}
+
+impl KeyCounts {
+    fn of(component: &RenderComponent) -> Self {
+        let mut keys = Self::default();
+        keys.add(component);
+        keys
+    }
+
+    /// Counts the keys of `component`, returning the first one that was
+    /// already used
+    fn add(&mut self, component: &RenderComponent) -> Option<String> {
+        let mut duplicate = None;
+        component.visit_keys(&mut |key| {
+            let count = self.counts.entry(key.to_string()).or_default();
+            *count += 1;
+            if *count > 1 && duplicate.is_none() {
+                duplicate = Some(key.to_string());
+            }
+            true
+        });
+        duplicate
+    }
+
+    fn remove(&mut self, component: &RenderComponent) {
+        component.visit_keys(&mut |key| {
+            if let Some(count) = self.counts.get_mut(key) {
+                *count -= 1;
+            }
+            true
+        });
+    }
+}
+
 impl RenderFactoryBox {
     fn build_component(&self) -> RenderComponent {
-        self.render.borrow_mut().render()
+        let handle = self.handle();
+        let previous = CURRENT_FACTORY.with(|current| current.replace(Some(handle)));
+        let component = self.render.borrow_mut().render();
+        CURRENT_FACTORY.with(|current| *current.borrow_mut() = previous);
+        match &self.key {
+            Some(key) => component.with_key(key.clone()),
+            None => component,
+        }
+    }
+
+    /// The components of the factory, built if needed. Unlike `component_mut`,
+    /// the reference isn't tied to a `RefCell` guard.
+    fn built_mut(&mut self) -> &mut RenderComponent {
+        if self.cached.get_mut().is_none() {
+            let component = self.build_component();
+            *self.cached.get_mut() = Some(component);
+        }
+        self.cached
+            .get_mut()
+            .as_mut()
+            .expect("the components were just built")
+    }
+
+    fn handle(&self) -> FactoryHandle {
+        FactoryHandle {
+            state: self.state.clone(),
+        }
+    }
+
+    /// Rebuilds the cached components if the factory was invalidated or wants
+    /// to be rebuilt, here or in a nested factory. A new tree using a key found
+    /// in `keys` is dropped, keeping the previous one. Returns whether anything
+    /// was rebuilt.
+    fn refresh(&mut self, keys: &mut KeyCounts) -> bool {
+        if let (Ok(mut redraw), Some(current)) = (self.state.redraw.lock(), RedrawHandle::current())
+        {
+            redraw.get_or_insert(current);
+        }
+        let is_stale = self.state.invalidated.swap(false, Ordering::SeqCst)
+            | self.render.get_mut().should_rebuild();
+        match self.cached.get_mut().take() {
+            Some(previous) if is_stale => {
+                let mut component = self.build_component();
+                keys.remove(&previous);
+                if let Some(key) = keys.add(&component) {
+                    keys.remove(&component);
+                    keys.add(&previous);
+                    keys.rejected = Some(key);
+                    *self.cached.get_mut() = Some(previous);
+                    return self.refresh_cached(keys);
+                }
+                component.reconcile(previous);
+                *self.cached.get_mut() = Some(component);
+                true
+            }
+            previous => {
+                *self.cached.get_mut() = previous;
+                self.refresh_cached(keys)
+            }
+        }
+    }
+
+    fn refresh_cached(&mut self, keys: &mut KeyCounts) -> bool {
+        self.cached
+            .get_mut()
+            .as_mut()
+            .is_some_and(|c| c.refresh_factories_in(keys))
     }
 
     fn cache(&self) -> RefMut<'_, RenderComponent> {
@@ -113,28 +343,349 @@ pub trait RenderFlow {
 
 pub struct RenderNode {
     pub id: RenderId,
+    /// Matches this node with the one of a previous tree, see `RenderComponent::reconcile`
+    pub key: Option<String>,
     pub focusable: bool,
     pub render: Box<dyn Render>,
+    pub(crate) area: Option<Rect>,
+}
+
+impl RenderNode {
+    /// The area this node was given in the last render, if it was rendered at all.
+    /// This is what hit-testing relies on.
+    pub fn area(&self) -> Option<Rect> {
+        self.area
+    }
+}
+
+/// A container splitting its area among its children, one constraint per child.
+///
+/// The margin is removed from the outside of the area and the padding from the
+/// inside of what is left, then the children are laid out leaving `spacing`
+/// cells between each of them.
+pub struct LayoutNode {
+    pub id: RenderId,
+    pub key: Option<String>,
+    pub direction: LayoutDirection,
+    pub margin: Margin,
+    pub padding: Padding,
+    pub spacing: u16,
+    pub(crate) constraints: Vec<Constraint>,
+    pub(crate) children: Vec<RenderComponent>,
+    pub(crate) handler: Option<LayoutEventHandler>,
+    pub(crate) area: Option<Rect>,
+}
+
+impl LayoutNode {
+    fn new(direction: LayoutDirection, children: Vec<(Constraint, RenderComponent)>) -> Self {
+        let (constraints, children) = children.into_iter().unzip();
+        Self {
+            id: RenderId::new(),
+            key: None,
+            direction,
+            margin: Margin::default(),
+            padding: Padding::zero(),
+            spacing: 0,
+            constraints,
+            children,
+            handler: None,
+            area: None,
+        }
+    }
+
+    /// Creates a column where the children evenly share the space
+    pub fn column(children: Vec<RenderComponent>) -> Self {
+        Self::column_with_constraints(
+            children
+                .into_iter()
+                .map(|c| (DEFAULT_CONSTRAINT, c))
+                .collect(),
+        )
+    }
+
+    /// Creates a row where the children evenly share the space
+    pub fn row(children: Vec<RenderComponent>) -> Self {
+        Self::row_with_constraints(
+            children
+                .into_iter()
+                .map(|c| (DEFAULT_CONSTRAINT, c))
+                .collect(),
+        )
+    }
+
+    /// Creates a column where each child takes the space given by its constraint
+    pub fn column_with_constraints(children: Vec<(Constraint, RenderComponent)>) -> Self {
+        Self::new(LayoutDirection::Column, children)
+    }
+
+    /// Creates a row where each child takes the space given by its constraint
+    pub fn row_with_constraints(children: Vec<(Constraint, RenderComponent)>) -> Self {
+        Self::new(LayoutDirection::Row, children)
+    }
+
+    /// See `RenderComponent::with_key`
+    pub fn with_key<S: Into<String>>(mut self, key: S) -> Self {
+        self.key = Some(key.into());
+        self
+    }
+
+    /// Sets the space left around the layout
+    pub fn with_margin(mut self, margin: Margin) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    /// Sets the space left between the edges of the layout and its children
+    pub fn with_padding(mut self, padding: Padding) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Sets the gap left between the children of the layout
+    pub fn with_spacing(mut self, spacing: u16) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    /// Handles the events ignored by the components inside the layout, before
+    /// they reach the page menu
+    pub fn with_event_handler<F>(mut self, handler: F) -> Self
+    where
+        F: FnMut(&InputEvent, &mut EventContext) -> EventResult + 'static,
+    {
+        self.handler = Some(Box::new(handler));
+        self
+    }
+
+    fn pad(area: Rect, padding: Padding) -> Rect {
+        let left = padding.left.min(area.width);
+        let top = padding.top.min(area.height);
+        Rect {
+            x: area.x + left,
+            y: area.y + top,
+            width: area.width - left - padding.right.min(area.width - left),
+            height: area.height - top - padding.bottom.min(area.height - top),
+        }
+    }
+
+    /// Calculates the area of each child
+    pub(crate) fn split(&self, area: Rect) -> Rc<[Rect]> {
+        Layout::new((&self.direction).into(), self.constraints.iter().copied())
+            .spacing(self.spacing)
+            .split(Self::pad(area.inner(&self.margin), self.padding))
+    }
 }
 
 pub enum RenderComponent {
-    Layout(RenderId, LayoutDirection, Vec<RenderComponent>),
+    Layout(LayoutNode),
     Render(RenderNode),
     Factory(Box<RenderFactoryBox>),
 }
 
+impl From<LayoutNode> for RenderComponent {
+    fn from(layout: LayoutNode) -> Self {
+        RenderComponent::Layout(layout)
+    }
+}
+
+/// A key on the keyboard, independent of the terminal backend
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum KeyCode {
+    Char(char),
+    Backspace,
+    Enter,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Tab,
+    BackTab,
+    Delete,
+    Insert,
+    F(u8),
+    Esc,
+    CapsLock,
+    ScrollLock,
+    NumLock,
+    PrintScreen,
+    Pause,
+    Menu,
+    KeypadBegin,
+    Null,
+}
+
+/// Set of modifier keys held while a key event happened
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct KeyModifiers(u8);
+
+impl KeyModifiers {
+    pub const NONE: Self = Self(0);
+    pub const SHIFT: Self = Self(1);
+    pub const CONTROL: Self = Self(1 << 1);
+    pub const ALT: Self = Self(1 << 2);
+    pub const SUPER: Self = Self(1 << 3);
+    pub const HYPER: Self = Self(1 << 4);
+    pub const META: Self = Self(1 << 5);
+
+    pub fn contains(&self, other: KeyModifiers) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl BitOr for KeyModifiers {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for KeyModifiers {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum KeyEventKind {
+    #[default]
+    Press,
+    Repeat,
+    Release,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct KeyEvent {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+    pub kind: KeyEventKind,
+}
+
+impl KeyEvent {
+    /// Creates a key press
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        Self {
+            code,
+            modifiers,
+            kind: KeyEventKind::Press,
+        }
+    }
+
+    pub fn with_kind(mut self, kind: KeyEventKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// The character typed by this event: a press (or repeat) of a character key
+    /// with no modifiers other than shift.
+    pub fn as_char(&self) -> Option<char> {
+        match self.code {
+            KeyCode::Char(c)
+                if self.kind != KeyEventKind::Release
+                    && (self.modifiers | KeyModifiers::SHIFT) == KeyModifiers::SHIFT =>
+            {
+                Some(c)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl From<KeyCode> for KeyEvent {
+    fn from(value: KeyCode) -> Self {
+        Self::new(value, KeyModifiers::NONE)
+    }
+}
+
+impl From<char> for KeyEvent {
+    fn from(value: char) -> Self {
+        KeyCode::Char(value).into()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ScrollDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
 #[derive(Clone, Debug)]
 pub enum InputEvent {
-    Key(char),
+    Key(KeyEvent),
     FocusWindow,
     FocusNext,
     FocusPrevious,
     Click(Position),
+    MouseDown(Position),
+    Drag(Position),
+    MouseMove(Position),
+    Scroll(ScrollDirection, Position),
+    Paste(String),
+    /// The terminal was resized to the given width and height
+    Resize(u16, u16),
+    /// A timer of the component fired, see `hooks::Timer`
+    Tick(TimerId),
+}
+
+impl InputEvent {
+    /// The character typed, if this is a key event typing one
+    pub fn as_char(&self) -> Option<char> {
+        match self {
+            InputEvent::Key(key) => key.as_char(),
+            _ => None,
+        }
+    }
+
+    /// The position of pointer events. These are delivered to the component under
+    /// the pointer rather than to the focused one.
+    pub fn position(&self) -> Option<Position> {
+        match self {
+            InputEvent::Click(position)
+            | InputEvent::MouseDown(position)
+            | InputEvent::Drag(position)
+            | InputEvent::MouseMove(position)
+            | InputEvent::Scroll(_, position) => Some(*position),
+            _ => None,
+        }
+    }
+
+    /// Makes the coordinates of positional events relative to `area`
+    pub(crate) fn relative_to(&self, area: Rect) -> Self {
+        let relative = |position: &Position| {
+            Position::new(
+                position.x.saturating_sub(area.x),
+                position.y.saturating_sub(area.y),
+            )
+        };
+        match self {
+            InputEvent::Click(position) => InputEvent::Click(relative(position)),
+            InputEvent::MouseDown(position) => InputEvent::MouseDown(relative(position)),
+            InputEvent::Drag(position) => InputEvent::Drag(relative(position)),
+            InputEvent::MouseMove(position) => InputEvent::MouseMove(relative(position)),
+            InputEvent::Scroll(direction, position) => {
+                InputEvent::Scroll(*direction, relative(position))
+            }
+            ev => ev.clone(),
+        }
+    }
+}
+
+fn relative_event(event: &InputEvent, area: Option<Rect>) -> InputEvent {
+    area.map_or_else(|| event.clone(), |area| event.relative_to(area))
 }
 
 pub struct VRenderProps<'a> {
     pub focused_element: Option<RenderId>,
-    pub event: Option<InputEvent>,
     pub alerts: &'a mut AlertManager,
 }
 
@@ -142,16 +693,20 @@ impl RenderComponent {
     pub fn new<T: Render + 'static>(render_fn: T) -> Self {
         Self::Render(RenderNode {
             id: RenderId::new(),
+            key: None,
             focusable: false,
             render: Box::new(render_fn),
+            area: None,
         })
     }
 
     pub fn new_focusable<T: Render + 'static>(render_fn: T) -> Self {
         Self::Render(RenderNode {
             id: RenderId::new(),
+            key: None,
             focusable: true,
             render: Box::new(render_fn),
+            area: None,
         })
     }
 
@@ -159,30 +714,266 @@ impl RenderComponent {
         Self::Factory(Box::new(RenderFactoryBox {
             cached: RefCell::new(None),
             render: RefCell::new(Box::new(render_factory)),
+            state: Arc::default(),
+            key: None,
         }))
     }
 
+    /// Names a component or layout, so that it keeps its state when its tree is
+    /// rebuilt. On a factory, the key goes to the root of every tree it builds.
+    pub fn with_key<S: Into<String>>(mut self, key: S) -> Self {
+        match &mut self {
+            RenderComponent::Layout(layout) => layout.key = Some(key.into()),
+            RenderComponent::Render(details) => details.key = Some(key.into()),
+            RenderComponent::Factory(factory) => {
+                let key = key.into();
+                if let Some(cached) = factory.cached.get_mut().take() {
+                    *factory.cached.get_mut() = Some(cached.with_key(key.clone()));
+                }
+                factory.key = Some(key);
+            }
+        }
+        self
+    }
+
+    pub fn key(&self) -> Option<&str> {
+        match self {
+            RenderComponent::Layout(layout) => layout.key.as_deref(),
+            RenderComponent::Render(details) => details.key.as_deref(),
+            RenderComponent::Factory(_) => None,
+        }
+    }
+
+    /// The id of the component with the given key. Layouts are skipped, as
+    /// they can't be focused.
+    pub fn id_of(&self, key: &str) -> Option<RenderId> {
+        let mut found = None;
+        self.visit(&mut |details| {
+            if details.key.as_deref() == Some(key) {
+                found = Some(details.id);
+            }
+            found.is_none()
+        });
+        found
+    }
+
+    /// The first key given to more than one component or layout of the tree
+    pub fn duplicate_key(&self) -> Option<String> {
+        let mut seen = HashSet::new();
+        let mut duplicate = None;
+        self.visit_keys(&mut |key| {
+            if !seen.insert(key.to_string()) {
+                duplicate = Some(key.to_string());
+            }
+            duplicate.is_none()
+        });
+        duplicate
+    }
+
+    fn visit_keys(&self, f: &mut dyn FnMut(&str) -> bool) -> bool {
+        if let Some(key) = self.key() {
+            if !f(key) {
+                return false;
+            }
+        }
+        match self {
+            RenderComponent::Layout(layout) => layout.children.iter().all(|c| c.visit_keys(f)),
+            RenderComponent::Render(_) => true,
+            RenderComponent::Factory(factory) => factory.component().visit_keys(f),
+        }
+    }
+
+    /// Carries over the ids and state of `previous`, the tree this one replaces:
+    /// the components with a key found in `previous` keep their `RenderId` and,
+    /// if they are of the same type, their widget (the new one is dropped).
+    /// Layouts with a key keep their id. Focus follows the ids.
+    pub fn reconcile(&mut self, previous: RenderComponent) {
+        let mut keyed = HashMap::new();
+        previous.collect_keyed(&mut keyed);
+        self.adopt_keyed(&mut keyed);
+    }
+
+    fn collect_keyed(self, keyed: &mut HashMap<String, RenderComponent>) {
+        match self {
+            RenderComponent::Layout(mut layout) => {
+                for c in std::mem::take(&mut layout.children) {
+                    c.collect_keyed(keyed)
+                }
+                if let Some(key) = layout.key.clone() {
+                    keyed.insert(key, RenderComponent::Layout(layout));
+                }
+            }
+            RenderComponent::Render(details) => {
+                if let Some(key) = details.key.clone() {
+                    keyed.insert(key, RenderComponent::Render(details));
+                }
+            }
+            RenderComponent::Factory(factory) => {
+                if let Some(c) = factory.cached.into_inner() {
+                    c.collect_keyed(keyed)
+                }
+            }
+        }
+    }
+
+    fn adopt_keyed(&mut self, keyed: &mut HashMap<String, RenderComponent>) {
+        let previous = self.key().and_then(|key| keyed.remove(key));
+        match (self, previous) {
+            (RenderComponent::Layout(layout), previous) => {
+                if let Some(RenderComponent::Layout(previous)) = previous {
+                    layout.id = previous.id;
+                }
+                for c in &mut layout.children {
+                    c.adopt_keyed(keyed)
+                }
+            }
+            (RenderComponent::Render(details), Some(RenderComponent::Render(previous))) => {
+                details.id = previous.id;
+                if Any::type_id(previous.render.as_any()) == Any::type_id(details.render.as_any()) {
+                    details.render = previous.render;
+                }
+            }
+            (RenderComponent::Factory(factory), _) => factory.built_mut().adopt_keyed(keyed),
+            _ => {}
+        }
+    }
+
+    /// The handle invalidating this factory, `None` for other components
+    pub fn factory_handle(&self) -> Option<FactoryHandle> {
+        match self {
+            RenderComponent::Factory(factory) => Some(factory.handle()),
+            _ => None,
+        }
+    }
+
+    /// Rebuilds the invalidated factories. A factory whose new components would
+    /// use a key found elsewhere in the tree keeps its previous ones.
+    pub(crate) fn refresh_factories(&mut self) -> FactoryRefresh {
+        let mut keys = KeyCounts::of(self);
+        let changed = self.refresh_factories_in(&mut keys);
+        FactoryRefresh {
+            changed,
+            duplicate_key: keys.rejected,
+        }
+    }
+
+    fn refresh_factories_in(&mut self, keys: &mut KeyCounts) -> bool {
+        match self {
+            RenderComponent::Layout(layout) => layout
+                .children
+                .iter_mut()
+                .fold(false, |changed, c| c.refresh_factories_in(keys) | changed),
+            RenderComponent::Render(_) => false,
+            RenderComponent::Factory(factory) => factory.refresh(keys),
+        }
+    }
+
     pub fn is_focusable(&self) -> bool {
         match self {
-            RenderComponent::Layout(_, _, _) => false,
+            RenderComponent::Layout(_) => false,
             RenderComponent::Render(details) => details.focusable,
             RenderComponent::Factory(_) => false,
         }
     }
 
     pub fn column(children: Vec<RenderComponent>) -> Self {
-        RenderComponent::Layout(RenderId::new(), LayoutDirection::Column, children)
+        LayoutNode::column(children).into()
     }
 
     pub fn row(children: Vec<RenderComponent>) -> Self {
-        RenderComponent::Layout(RenderId::new(), LayoutDirection::Row, children)
+        LayoutNode::row(children).into()
     }
 
-    pub fn flatten_ids(&self) -> Vec<RenderId> {
+    /// Creates a column where each child takes the space given by its constraint.
+    /// See `LayoutNode::column_with_constraints` to set its margin, padding,
+    /// spacing or event handler.
+    pub fn column_with_constraints(children: Vec<(Constraint, RenderComponent)>) -> Self {
+        LayoutNode::column_with_constraints(children).into()
+    }
+
+    /// Creates a row where each child takes the space given by its constraint.
+    /// See `LayoutNode::row_with_constraints` to set its margin, padding,
+    /// spacing or event handler.
+    pub fn row_with_constraints(children: Vec<(Constraint, RenderComponent)>) -> Self {
+        LayoutNode::row_with_constraints(children).into()
+    }
+
+    /// Delivers an event to the `target` component. While it is ignored, it
+    /// bubbles up to the target's menu and to the handlers of the layouts
+    /// containing it. Positional events are made relative to the area of
+    /// whoever handles them in the last render.
+    ///
+    /// Returns `None` when the target is not part of this tree.
+    pub fn dispatch_event(
+        &mut self,
+        target: &RenderId,
+        event: &InputEvent,
+        alerts: &mut AlertManager,
+    ) -> Option<EventResult> {
         match self {
-            RenderComponent::Layout(_, _, children) => {
-                children.iter().flat_map(|c| c.flatten_ids()).collect()
+            RenderComponent::Layout(layout) => {
+                let result = layout
+                    .children
+                    .iter_mut()
+                    .find_map(|c| c.dispatch_event(target, event, alerts))?;
+                match (&mut layout.handler, result) {
+                    (Some(handler), EventResult::Ignored) => Some(handler(
+                        &relative_event(event, layout.area),
+                        &mut EventContext {
+                            id: layout.id,
+                            alerts,
+                            focus: FocusHandle::current(),
+                        },
+                    )),
+                    _ => Some(result),
+                }
             }
+            RenderComponent::Render(details) if details.id == *target => {
+                let _component = enter_component(details.id);
+                let event = relative_event(event, details.area);
+                let mut result = details.render.on_event(
+                    &event,
+                    &mut EventContext {
+                        id: details.id,
+                        alerts,
+                        focus: FocusHandle::current(),
+                    },
+                );
+                if !result.is_consumed() {
+                    if let Some(mut menu) = details.render.get_menu() {
+                        result = menu.handle_event(alerts, &event);
+                    }
+                }
+                Some(result)
+            }
+            RenderComponent::Render(_) => None,
+            RenderComponent::Factory(factory) => factory
+                .component_mut()
+                .dispatch_event(target, event, alerts),
+        }
+    }
+
+    /// Forgets the areas recorded in the last render, until the next one
+    pub(crate) fn clear_areas(&mut self) {
+        match self {
+            RenderComponent::Layout(layout) => {
+                layout.area = None;
+                for c in &mut layout.children {
+                    c.clear_areas()
+                }
+            }
+            RenderComponent::Render(details) => details.area = None,
+            RenderComponent::Factory(factory) => factory.component_mut().clear_areas(),
+        }
+    }
+
+    pub fn flatten_ids(&self) -> Vec<RenderId> {
+        match self {
+            RenderComponent::Layout(layout) => layout
+                .children
+                .iter()
+                .flat_map(|c| c.flatten_ids())
+                .collect(),
             RenderComponent::Render(details) => {
                 if details.focusable {
                     vec![details.id]
@@ -196,8 +987,8 @@ impl RenderComponent {
 
     pub fn visit_with_downcast<T: Render + Any>(&self, f: &mut dyn FnMut(Option<&T>)) {
         match self {
-            RenderComponent::Layout(_, _, children) => {
-                for c in children {
+            RenderComponent::Layout(layout) => {
+                for c in &layout.children {
                     c.visit_with_downcast(f)
                 }
             }
@@ -209,8 +1000,8 @@ impl RenderComponent {
     }
     pub fn visit(&self, f: &mut dyn FnMut(&RenderNode) -> bool) -> bool {
         match self {
-            RenderComponent::Layout(_, _, children) => {
-                for c in children {
+            RenderComponent::Layout(layout) => {
+                for c in &layout.children {
                     if !c.visit(f) {
                         return false;
                     }
@@ -221,6 +1012,89 @@ impl RenderComponent {
             RenderComponent::Factory(factory) => factory.component().visit(f),
         }
     }
+
+    /// Like `visit`, with mutable access to the nodes. Stale factories are
+    /// rebuilt first, so that the changes aren't lost with their old
+    /// components; the pages call it after their own rebuild, which records a
+    /// rebuild dropped for a duplicate key.
+    pub(crate) fn visit_mut(&mut self, f: &mut dyn FnMut(&mut RenderNode) -> bool) -> bool {
+        self.refresh_factories();
+        self.visit_nodes_mut(f)
+    }
+
+    fn visit_nodes_mut(&mut self, f: &mut dyn FnMut(&mut RenderNode) -> bool) -> bool {
+        match self {
+            RenderComponent::Layout(layout) => {
+                for c in &mut layout.children {
+                    if !c.visit_nodes_mut(f) {
+                        return false;
+                    }
+                }
+                true
+            }
+            RenderComponent::Render(details) => f(details),
+            RenderComponent::Factory(factory) => factory.built_mut().visit_nodes_mut(f),
+        }
+    }
+
+    /// The first node matching `predicate`, in render order, rebuilding the
+    /// stale factories first like `visit_mut`
+    pub(crate) fn find_node_mut(
+        &mut self,
+        predicate: &dyn Fn(&RenderNode) -> bool,
+    ) -> Option<&mut RenderNode> {
+        self.refresh_factories();
+        self.find_node_in(predicate)
+    }
+
+    fn find_node_in(&mut self, predicate: &dyn Fn(&RenderNode) -> bool) -> Option<&mut RenderNode> {
+        match self {
+            RenderComponent::Layout(layout) => layout
+                .children
+                .iter_mut()
+                .find_map(|c| c.find_node_in(predicate)),
+            RenderComponent::Render(details) => predicate(details).then_some(details),
+            RenderComponent::Factory(factory) => factory.built_mut().find_node_in(predicate),
+        }
+    }
+
+    /// The widget of the component with the given id, if it is a `T`
+    pub(crate) fn find_by_id<T: Render + Any>(&mut self, id: &RenderId) -> Option<&mut T> {
+        self.find_node_mut(&|details| details.id == *id)?
+            .render
+            .as_any_mut()
+            .downcast_mut()
+    }
+
+    /// The widget of the component with the given key, if it is a `T`
+    pub(crate) fn find_by_key<T: Render + Any>(&mut self, key: &str) -> Option<&mut T> {
+        self.find_node_mut(&|details| details.key.as_deref() == Some(key))?
+            .render
+            .as_any_mut()
+            .downcast_mut()
+    }
+
+    /// Every widget that is a `T`, in render order
+    pub(crate) fn find_all<T: Render + Any>(&mut self) -> Vec<&mut T> {
+        self.refresh_factories();
+        let mut found = vec![];
+        self.collect_all(&mut found);
+        found
+    }
+
+    fn collect_all<'a, T: Render + Any>(&'a mut self, found: &mut Vec<&'a mut T>) {
+        match self {
+            RenderComponent::Layout(layout) => {
+                for c in &mut layout.children {
+                    c.collect_all(found)
+                }
+            }
+            RenderComponent::Render(details) => {
+                found.extend(details.render.as_any_mut().downcast_mut::<T>())
+            }
+            RenderComponent::Factory(factory) => factory.built_mut().collect_all(found),
+        }
+    }
 }
 
 impl RenderFlow for RenderComponent {
@@ -232,21 +1106,17 @@ impl RenderFlow for RenderComponent {
         area: Rect,
     ) {
         match self {
-            RenderComponent::Layout(_, layout, children) => {
-                let layout = ratatui::layout::Layout::new(
-                    match layout {
-                        LayoutDirection::Column => Direction::Vertical,
-                        LayoutDirection::Row => Direction::Horizontal,
-                    },
-                    children.iter().map(|_| Constraint::Fill(1)),
-                )
-                .split(area);
+            RenderComponent::Layout(layout) => {
+                layout.area = Some(area);
+                let areas = layout.split(area);
 
-                for (a, c) in layout.iter().zip(children.iter_mut()) {
+                for (a, c) in areas.iter().zip(layout.children.iter_mut()) {
                     c.render(opts, component_buffer, buff, *a);
                 }
             }
             RenderComponent::Render(details) => {
+                let _component = enter_component(details.id);
+                details.area = Some(area);
                 let is_focused = opts
                     .focused_element
                     .map(|fid| fid == details.id)
@@ -255,7 +1125,6 @@ impl RenderFlow for RenderComponent {
                     &RenderProps {
                         is_focused,
                         alerts: opts.alerts,
-                        event: if is_focused { opts.event.clone() } else { None },
                         event_buffer: component_buffer.get_buffer(&details.id),
                     },
                     buff,
@@ -273,9 +1142,11 @@ impl RenderFlow for RenderComponent {
 
     fn get_focusable_elements(&self) -> Vec<RenderId> {
         match self {
-            RenderComponent::Layout(_, _, children) => {
-                children.iter().flat_map(|c| c.flatten_ids()).collect()
-            }
+            RenderComponent::Layout(layout) => layout
+                .children
+                .iter()
+                .flat_map(|c| c.flatten_ids())
+                .collect(),
             RenderComponent::Render(details) => {
                 if details.focusable {
                     vec![details.id]
@@ -318,11 +1189,15 @@ mod tests {
     use crate::core::RenderId;
     use crate::macros::column_widget;
     use crate::row_widget;
+    use crate::testing::{render_to_buffer, Snapshot};
     use crate::windows::alerts::AlertManager;
     use ratatui::buffer::Buffer;
+    use ratatui::layout::{Constraint, Margin, Position, Rect};
+    use ratatui::widgets::{Block, Borders, Padding, Paragraph, Widget};
 
     use super::{
-        ComponentBuffer, InputEvent, LoopManager, Render, RenderFactory, RenderProps, VRenderProps,
+        ComponentBuffer, EventContext, EventResult, InputEvent, LoopManager, Render, RenderFactory,
+        RenderProps, VRenderProps,
     };
 
     use super::RenderComponent;
@@ -343,15 +1218,21 @@ mod tests {
     }
 
     impl Render for TestRender {
+        fn on_event(&mut self, event: &InputEvent, _ctx: &mut EventContext) -> EventResult {
+            if let Some(c) = event.as_char() {
+                self.text_content.push(c);
+                EventResult::Consumed
+            } else {
+                EventResult::Ignored
+            }
+        }
+
         fn render(
             &mut self,
             render_props: &RenderProps,
             _buff: &mut Buffer,
             _area: ratatui::prelude::Rect,
         ) {
-            if let Some(InputEvent::Key(k)) = &render_props.event {
-                self.text_content.push(*k)
-            }
             println!(
                 "{}:{} (focused?:{})",
                 self.name, self.text_content, render_props.is_focused
@@ -472,9 +1353,9 @@ mod tests {
             vec![f1, f1, f1, f2, f2, f2],
             vec![
                 None,
-                Some(InputEvent::Key('a')),
-                Some(InputEvent::Key('b')),
-                Some(InputEvent::Key('c')),
+                Some(InputEvent::Key('a'.into())),
+                Some(InputEvent::Key('b'.into())),
+                Some(InputEvent::Key('c'.into())),
             ],
         );
 
@@ -495,11 +1376,14 @@ mod tests {
                 i, event, focused_element
             );
             event_buffer.add_event(focused_element.unwrap(), &event);
+            let mut alerts = AlertManager::default();
+            if let (Some(ev), Some(target)) = (&event, &focused_element) {
+                app.dispatch_event(target, ev, &mut alerts);
+            }
             app.render(
                 &mut VRenderProps {
                     focused_element,
-                    event,
-                    alerts: &mut AlertManager::default(),
+                    alerts: &mut alerts,
                 },
                 &mut event_buffer,
                 &mut buffer,
@@ -509,11 +1393,12 @@ mod tests {
     }
     #[test]
     fn test_macros() {
-        let mut app = column_widget!(
+        let mut app: RenderComponent = column_widget!(
             TestRender::new("c1"),
             RenderComponent::new_focusable(TestRender::new("c2")),
             RenderComponent::new_factory(TestFactory {}),
-        );
+        )
+        .into();
 
         run_loop(&mut app);
         app.visit_with_downcast::<TestRender>(&mut |x| {
@@ -529,11 +1414,12 @@ mod tests {
 
     #[test]
     fn test_visit() {
-        let app = column_widget!(
+        let app: RenderComponent = column_widget!(
             TestRender::new("c1"),
             TestRender::new("c2"),
             row_widget!(TestRender::new("c3"), TestRender::new("c4"))
-        );
+        )
+        .into();
 
         let mut visited = vec![];
 
@@ -547,11 +1433,12 @@ mod tests {
 
     #[test]
     fn test_visit_with_factory() {
-        let app = column_widget!(
+        let app: RenderComponent = column_widget!(
             TestRender::new("c1"),
             RenderComponent::new_focusable(TestRender::new("c2")),
             RenderComponent::new_factory(TestFactory {}),
-        );
+        )
+        .into();
 
         let mut visited = vec![];
 
@@ -564,4 +1451,419 @@ mod tests {
 
         assert_eq!(visited.len(), 4);
     }
+
+    #[test]
+    fn test_find_and_visit_mut() {
+        let mut app: RenderComponent = column_widget!(
+            TestRender::new("c1"),
+            RenderComponent::new_focusable(TestRender::new("c2")).with_key("c2"),
+            RenderComponent::new_factory(TestFactory {}),
+            AreaRender::default(),
+        )
+        .into();
+        let ids = app.flatten_ids();
+
+        app.find_by_key::<TestRender>("c2")
+            .unwrap()
+            .text_content
+            .push_str("updated");
+        assert!(app.find_by_key::<AreaRender>("c2").is_none());
+        assert!(app.find_by_key::<TestRender>("missing").is_none());
+
+        // components built by factories are found too
+        app.find_by_id::<TestRender>(&ids[2])
+            .unwrap()
+            .text_content
+            .push_str("from factory");
+
+        let names: Vec<_> = app
+            .find_all::<TestRender>()
+            .into_iter()
+            .map(|r| format!("{}:{}", r.name, r.text_content))
+            .collect();
+        assert_eq!(names, vec!["c1:", "c2:updated", "c3:", "c4:from factory"]);
+
+        let mut visited = 0;
+        app.visit_mut(&mut |details| {
+            visited += 1;
+            details.focusable = false;
+            visited < 3
+        });
+        assert_eq!(visited, 3);
+        assert_eq!(app.flatten_ids(), vec![ids[2]]);
+    }
+
+    #[test]
+    fn test_keys_in_macros() {
+        let app: RenderComponent = column_widget!(
+            TestRender::new("c1") => "first",
+            row_widget!(
+                TestRender::new("c2") => (Constraint::Length(3), "second"),
+                TestRender::new("c3") => Constraint::Length(2),
+            ) => "row",
+        )
+        .into();
+        let mut ids = vec![];
+        app.visit(&mut |details| {
+            ids.push(details.id);
+            true
+        });
+
+        assert_eq!(app.id_of("first"), Some(ids[0]));
+        assert_eq!(app.id_of("second"), Some(ids[1]));
+        // layouts can't be focused
+        assert_eq!(app.id_of("row"), None);
+        assert_eq!(app.duplicate_key(), None);
+
+        let app: RenderComponent = row_widget!(
+            TestRender::new("c1") => "same",
+            column_widget!(TestRender::new("c2")) => "same",
+        )
+        .into();
+        assert_eq!(app.duplicate_key(), Some("same".to_string()));
+    }
+
+    struct NodeFactory {}
+
+    impl RenderFactory for NodeFactory {
+        fn render(&mut self) -> RenderComponent {
+            RenderComponent::new_focusable(TestRender::new("c3"))
+        }
+    }
+
+    #[test]
+    fn test_keyed_factory() {
+        let factory = RenderComponent::new_factory(NodeFactory {}).with_key("factory");
+        let handle = factory.factory_handle().unwrap();
+        let mut app: RenderComponent =
+            row_widget!(factory, TestRender::new("c1") => "other").into();
+        let id = app.id_of("factory");
+        assert!(id.is_some());
+
+        // the key goes to every tree built, so the rebuilt node keeps its id
+        handle.invalidate();
+        assert!(app.refresh_factories().changed);
+        assert_eq!(app.id_of("factory"), id);
+
+        let app: RenderComponent = row_widget!(
+            RenderComponent::new_factory(NodeFactory {}).with_key("same"),
+            TestRender::new("c1") => "same",
+        )
+        .into();
+        assert_eq!(app.duplicate_key(), Some("same".to_string()));
+    }
+
+    #[derive(Default)]
+    struct AreaRender {
+        area: Rect,
+    }
+
+    impl Render for AreaRender {
+        fn render(&mut self, _render_props: &RenderProps, _buff: &mut Buffer, area: Rect) {
+            self.area = area;
+        }
+    }
+
+    #[test]
+    fn test_layout_constraints() {
+        let mut app: RenderComponent = row_widget!(
+            AreaRender::default() => Constraint::Length(30),
+            column_widget!(
+                AreaRender::default() => Constraint::Length(3),
+                AreaRender::default(),
+                AreaRender::default() => Constraint::Percentage(10),
+            ),
+        )
+        .into();
+
+        let area = Rect::new(0, 0, 100, 50);
+        app.render(
+            &mut VRenderProps {
+                focused_element: None,
+                alerts: &mut AlertManager::default(),
+            },
+            &mut ComponentBuffer::default(),
+            &mut Buffer::empty(area),
+            area,
+        );
+
+        let mut areas = vec![];
+        app.visit_with_downcast::<AreaRender>(&mut |x| areas.push(x.unwrap().area));
+
+        assert_eq!(
+            areas,
+            vec![
+                Rect::new(0, 0, 30, 50),
+                Rect::new(30, 0, 70, 3),
+                Rect::new(30, 3, 70, 42),
+                Rect::new(30, 45, 70, 5),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_layout_margin_padding_and_spacing() {
+        let mut app: RenderComponent = row_widget!(AreaRender::default(), AreaRender::default())
+            .with_margin(Margin::new(2, 1))
+            .with_padding(Padding::new(1, 0, 2, 0))
+            .with_spacing(3)
+            .into();
+
+        let area = Rect::new(0, 0, 40, 20);
+        app.render(
+            &mut VRenderProps {
+                focused_element: None,
+                alerts: &mut AlertManager::default(),
+            },
+            &mut ComponentBuffer::default(),
+            &mut Buffer::empty(area),
+            area,
+        );
+
+        let mut areas = vec![];
+        app.visit_with_downcast::<AreaRender>(&mut |x| areas.push(x.unwrap().area));
+
+        assert_eq!(
+            areas,
+            vec![Rect::new(3, 3, 16, 16), Rect::new(22, 3, 16, 16)]
+        );
+    }
+
+    struct BoxRender(&'static str);
+
+    impl Render for BoxRender {
+        fn render(&mut self, _render_props: &RenderProps, buff: &mut Buffer, area: Rect) {
+            Paragraph::new(self.0)
+                .block(Block::new().borders(Borders::all()))
+                .render(area, buff)
+        }
+    }
+
+    #[test]
+    fn test_layout_snapshot() {
+        let mut app: RenderComponent = row_widget!(
+            BoxRender("left") => Constraint::Length(10),
+            column_widget!(BoxRender("top"), BoxRender("bottom")).with_spacing(1),
+        )
+        .with_margin(Margin::new(1, 0))
+        .into();
+
+        Snapshot::new("core_layout").assert(&render_to_buffer(&mut app, 30, 8));
+    }
+
+    #[derive(Default)]
+    struct ClickRender {
+        clicks: Vec<Position>,
+        renders: usize,
+    }
+
+    impl Render for ClickRender {
+        fn on_event(&mut self, event: &InputEvent, _ctx: &mut EventContext) -> EventResult {
+            if let InputEvent::Click(position) = event {
+                self.clicks.push(*position);
+                EventResult::Consumed
+            } else {
+                EventResult::Ignored
+            }
+        }
+
+        fn render(&mut self, _render_props: &RenderProps, _buff: &mut Buffer, _area: Rect) {
+            self.renders += 1;
+        }
+    }
+
+    fn render_once(app: &mut RenderComponent, area: Rect) {
+        app.render(
+            &mut VRenderProps {
+                focused_element: None,
+                alerts: &mut AlertManager::default(),
+            },
+            &mut ComponentBuffer::default(),
+            &mut Buffer::empty(area),
+            area,
+        );
+    }
+
+    #[test]
+    fn test_click_is_relative_to_component() {
+        let mut app: RenderComponent = row_widget!(
+            RenderComponent::new_focusable(ClickRender::default()),
+            RenderComponent::new_focusable(ClickRender::default()),
+        )
+        .into();
+        let ids = app.flatten_ids();
+
+        render_once(&mut app, Rect::new(0, 0, 20, 10));
+        let result = app.dispatch_event(
+            &ids[1],
+            &InputEvent::Click(Position::new(13, 4)),
+            &mut AlertManager::default(),
+        );
+        assert_eq!(result, Some(EventResult::Consumed));
+
+        let mut clicks = vec![];
+        app.visit_with_downcast::<ClickRender>(&mut |x| clicks.push(x.unwrap().clicks.clone()));
+
+        assert_eq!(clicks, vec![vec![], vec![Position::new(3, 4)]]);
+    }
+
+    #[test]
+    fn test_redraw_does_not_handle_events() {
+        let mut app: RenderComponent =
+            row_widget!(RenderComponent::new_focusable(ClickRender::default())).into();
+        let id = app.flatten_ids()[0];
+
+        // events can be handled before anything was drawn
+        app.dispatch_event(
+            &id,
+            &InputEvent::Click(Position::new(1, 1)),
+            &mut AlertManager::default(),
+        );
+        render_once(&mut app, Rect::new(0, 0, 20, 10));
+        render_once(&mut app, Rect::new(0, 0, 20, 10));
+
+        app.visit_with_downcast::<ClickRender>(&mut |x| {
+            let x = x.unwrap();
+            assert_eq!(x.clicks, vec![Position::new(1, 1)]);
+            assert_eq!(x.renders, 2);
+        });
+    }
+
+    #[test]
+    fn test_dispatch_to_unknown_target() {
+        let mut app: RenderComponent =
+            row_widget!(RenderComponent::new_focusable(ClickRender::default())).into();
+
+        assert_eq!(
+            app.dispatch_event(
+                &RenderId::new(),
+                &InputEvent::FocusNext,
+                &mut AlertManager::default()
+            ),
+            None
+        );
+    }
+
+    /// The id and the text of the `TestRender` of every keyed component
+    fn keyed_nodes(app: &RenderComponent) -> Vec<(String, RenderId, Option<String>)> {
+        let mut nodes = vec![];
+        app.visit(&mut |details| {
+            if let Some(key) = &details.key {
+                let text = details
+                    .render
+                    .as_any()
+                    .downcast_ref::<TestRender>()
+                    .map(|r| r.text_content.clone());
+                nodes.push((key.clone(), details.id, text));
+            }
+            true
+        });
+        nodes
+    }
+
+    #[test]
+    fn test_reconcile_by_key() {
+        let mut previous: RenderComponent = row_widget!(
+            RenderComponent::new_focusable(TestRender::new("a")).with_key("a"),
+            RenderComponent::new(TestRender::new("b")).with_key("b"),
+            TestRender::new("c"),
+        )
+        .with_key("root")
+        .into();
+        let unkeyed_id = |app: &RenderComponent| {
+            let mut id = None;
+            app.visit(&mut |details| {
+                id = id.or(details.key.is_none().then_some(details.id));
+                true
+            });
+            id
+        };
+        let previous_unkeyed = unkeyed_id(&previous);
+        let previous_nodes = keyed_nodes(&previous);
+        let RenderComponent::Layout(layout) = &previous else {
+            unreachable!()
+        };
+        let root_id = layout.id;
+        previous.dispatch_event(
+            &previous_nodes[0].1,
+            &InputEvent::Key('x'.into()),
+            &mut AlertManager::default(),
+        );
+
+        let mut app: RenderComponent = column_widget!(
+            TestRender::new("c"),
+            row_widget!(RenderComponent::new_focusable(TestRender::new("a")).with_key("a")),
+            RenderComponent::new(AreaRender::default()).with_key("b"),
+        )
+        .with_key("root")
+        .into();
+        app.reconcile(previous);
+
+        let nodes = keyed_nodes(&app);
+        // same id and state
+        assert_eq!(
+            nodes[0],
+            ("a".to_string(), previous_nodes[0].1, Some("x".to_string()))
+        );
+        // same id, but a different widget
+        assert_eq!(nodes[1], ("b".to_string(), previous_nodes[1].1, None));
+        let RenderComponent::Layout(layout) = &app else {
+            unreachable!()
+        };
+        assert_eq!(layout.id, root_id);
+        // components without a key are new
+        assert_ne!(unkeyed_id(&app), previous_unkeyed);
+    }
+
+    struct InnerFactory {}
+
+    impl RenderFactory for InnerFactory {
+        fn render(&mut self) -> RenderComponent {
+            RenderComponent::new_focusable(TestRender::new("inner")).with_key("inner")
+        }
+    }
+
+    struct OuterFactory {}
+
+    impl RenderFactory for OuterFactory {
+        fn render(&mut self) -> RenderComponent {
+            row_widget!(
+                TestRender::new("outer"),
+                RenderComponent::new_factory(InnerFactory {})
+            )
+            .into()
+        }
+    }
+
+    #[test]
+    fn test_reconcile_nested_factories() {
+        let outer = RenderComponent::new_factory(OuterFactory {});
+        let handle = outer.factory_handle().unwrap();
+        let mut app: RenderComponent = row_widget!(outer).into();
+        app.find_by_key::<TestRender>("inner").unwrap().text_content = "x".into();
+        let id = app.id_of("inner");
+        assert!(id.is_some());
+
+        // the parent factory is rebuilt
+        handle.invalidate();
+        assert!(app.refresh_factories().changed);
+        assert_eq!(app.id_of("inner"), id);
+        assert_eq!(
+            app.find_by_key::<TestRender>("inner").unwrap().text_content,
+            "x"
+        );
+
+        // the whole tree is replaced
+        let mut replaced: RenderComponent =
+            row_widget!(RenderComponent::new_factory(OuterFactory {})).into();
+        replaced.reconcile(app);
+        assert_eq!(replaced.id_of("inner"), id);
+        assert_eq!(
+            replaced
+                .find_by_key::<TestRender>("inner")
+                .unwrap()
+                .text_content,
+            "x"
+        );
+    }
 }
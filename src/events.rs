@@ -0,0 +1,615 @@
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    io,
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, Sender, SyncSender},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use crossterm::event::{self, Event, MouseEventKind};
+use ratatui::layout::Position;
+
+use crate::{
+    core::{InputEvent, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, RenderId, ScrollDirection},
+    hooks::TimerId,
+};
+
+pub trait EventMapper {
+    fn to_input_event(ev: &crossterm::event::Event) -> Option<InputEvent>;
+}
+
+#[derive(Default)]
+pub struct DefaultEventMapper {}
+
+impl DefaultEventMapper {
+    /// Converts a Crossterm key event, for reuse by custom mappers. Media and
+    /// modifier-only keys are not supported.
+    pub fn to_key_event(key: &event::KeyEvent) -> Option<KeyEvent> {
+        let code = match key.code {
+            event::KeyCode::Char(c) => KeyCode::Char(c),
+            event::KeyCode::Backspace => KeyCode::Backspace,
+            event::KeyCode::Enter => KeyCode::Enter,
+            event::KeyCode::Left => KeyCode::Left,
+            event::KeyCode::Right => KeyCode::Right,
+            event::KeyCode::Up => KeyCode::Up,
+            event::KeyCode::Down => KeyCode::Down,
+            event::KeyCode::Home => KeyCode::Home,
+            event::KeyCode::End => KeyCode::End,
+            event::KeyCode::PageUp => KeyCode::PageUp,
+            event::KeyCode::PageDown => KeyCode::PageDown,
+            event::KeyCode::Tab => KeyCode::Tab,
+            event::KeyCode::BackTab => KeyCode::BackTab,
+            event::KeyCode::Delete => KeyCode::Delete,
+            event::KeyCode::Insert => KeyCode::Insert,
+            event::KeyCode::F(n) => KeyCode::F(n),
+            event::KeyCode::Esc => KeyCode::Esc,
+            event::KeyCode::CapsLock => KeyCode::CapsLock,
+            event::KeyCode::ScrollLock => KeyCode::ScrollLock,
+            event::KeyCode::NumLock => KeyCode::NumLock,
+            event::KeyCode::PrintScreen => KeyCode::PrintScreen,
+            event::KeyCode::Pause => KeyCode::Pause,
+            event::KeyCode::Menu => KeyCode::Menu,
+            event::KeyCode::KeypadBegin => KeyCode::KeypadBegin,
+            event::KeyCode::Null => KeyCode::Null,
+            event::KeyCode::Media(_) | event::KeyCode::Modifier(_) => return None,
+        };
+
+        let mut modifiers = KeyModifiers::NONE;
+        for (from, to) in [
+            (event::KeyModifiers::SHIFT, KeyModifiers::SHIFT),
+            (event::KeyModifiers::CONTROL, KeyModifiers::CONTROL),
+            (event::KeyModifiers::ALT, KeyModifiers::ALT),
+            (event::KeyModifiers::SUPER, KeyModifiers::SUPER),
+            (event::KeyModifiers::HYPER, KeyModifiers::HYPER),
+            (event::KeyModifiers::META, KeyModifiers::META),
+        ] {
+            if key.modifiers.contains(from) {
+                modifiers |= to;
+            }
+        }
+
+        let kind = match key.kind {
+            event::KeyEventKind::Press => KeyEventKind::Press,
+            event::KeyEventKind::Repeat => KeyEventKind::Repeat,
+            event::KeyEventKind::Release => KeyEventKind::Release,
+        };
+
+        Some(KeyEvent::new(code, modifiers).with_kind(kind))
+    }
+}
+
+impl EventMapper for DefaultEventMapper {
+    fn to_input_event(ev: &crossterm::event::Event) -> Option<InputEvent> {
+        match ev {
+            Event::FocusGained => None,
+            Event::FocusLost => None,
+            Event::Key(key) => {
+                let key = Self::to_key_event(key)?;
+                match key {
+                    KeyEvent {
+                        kind: KeyEventKind::Release,
+                        ..
+                    } => Some(InputEvent::Key(key)),
+                    KeyEvent {
+                        code: KeyCode::BackTab,
+                        ..
+                    } => Some(InputEvent::FocusPrevious),
+                    KeyEvent {
+                        code: KeyCode::Tab,
+                        modifiers,
+                        ..
+                    } if modifiers == KeyModifiers::SHIFT => Some(InputEvent::FocusPrevious),
+                    KeyEvent {
+                        code: KeyCode::Tab, ..
+                    } => Some(InputEvent::FocusNext),
+                    KeyEvent {
+                        code: KeyCode::Esc, ..
+                    } => Some(InputEvent::FocusWindow),
+                    key => Some(InputEvent::Key(key)),
+                }
+            }
+            Event::Mouse(mouse_event) => {
+                let position = Position::new(mouse_event.column, mouse_event.row);
+                Some(match mouse_event.kind {
+                    MouseEventKind::Down(_) => InputEvent::MouseDown(position),
+                    MouseEventKind::Up(_) => InputEvent::Click(position),
+                    MouseEventKind::Drag(_) => InputEvent::Drag(position),
+                    MouseEventKind::Moved => InputEvent::MouseMove(position),
+                    MouseEventKind::ScrollDown => {
+                        InputEvent::Scroll(ScrollDirection::Down, position)
+                    }
+                    MouseEventKind::ScrollUp => InputEvent::Scroll(ScrollDirection::Up, position),
+                    MouseEventKind::ScrollLeft => {
+                        InputEvent::Scroll(ScrollDirection::Left, position)
+                    }
+                    MouseEventKind::ScrollRight => {
+                        InputEvent::Scroll(ScrollDirection::Right, position)
+                    }
+                })
+            }
+            Event::Paste(text) => Some(InputEvent::Paste(text.clone())),
+            Event::Resize(width, height) => Some(InputEvent::Resize(*width, *height)),
+        }
+    }
+}
+
+/// Where a `Window` takes its events from
+pub trait EventSource {
+    /// Waits up to `timeout` for the next event
+    fn next_event(&mut self, timeout: Duration) -> io::Result<Option<InputEvent>>;
+
+    /// Whether no more events will ever come
+    fn is_closed(&self) -> bool {
+        false
+    }
+}
+
+/// Reads the terminal events with Crossterm, converting them with an `EventMapper`
+pub struct CrosstermEventSource<T: EventMapper = DefaultEventMapper> {
+    mapper: PhantomData<T>,
+}
+
+impl<T: EventMapper> CrosstermEventSource<T> {
+    pub fn new() -> Self {
+        Self {
+            mapper: PhantomData,
+        }
+    }
+}
+
+impl<T: EventMapper> Default for CrosstermEventSource<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: EventMapper> EventSource for CrosstermEventSource<T> {
+    fn next_event(&mut self, timeout: Duration) -> io::Result<Option<InputEvent>> {
+        if event::poll(timeout)? {
+            Ok(T::to_input_event(&event::read()?))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Takes the events sent from other threads. It is closed once every sender
+/// is dropped.
+pub struct ChannelEventSource {
+    receiver: Receiver<InputEvent>,
+    closed: bool,
+}
+
+impl ChannelEventSource {
+    pub fn new(receiver: Receiver<InputEvent>) -> Self {
+        Self {
+            receiver,
+            closed: false,
+        }
+    }
+
+    /// Creates a source together with the sender feeding it
+    pub fn channel() -> (Sender<InputEvent>, Self) {
+        let (tx, rx) = mpsc::channel();
+        (tx, Self::new(rx))
+    }
+}
+
+impl EventSource for ChannelEventSource {
+    fn next_event(&mut self, timeout: Duration) -> io::Result<Option<InputEvent>> {
+        match self.receiver.recv_timeout(timeout) {
+            Ok(ev) => Ok(Some(ev)),
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(RecvTimeoutError::Disconnected) => {
+                self.closed = true;
+                Ok(None)
+            }
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed
+    }
+}
+
+/// Hands out a predefined sequence of events without waiting, e.g. for tests.
+/// It is closed once all of them were taken.
+#[derive(Default)]
+pub struct ScriptedEventSource {
+    events: VecDeque<InputEvent>,
+}
+
+impl ScriptedEventSource {
+    pub fn new<I: IntoIterator<Item = InputEvent>>(events: I) -> Self {
+        Self {
+            events: events.into_iter().collect(),
+        }
+    }
+
+    pub fn push(&mut self, event: InputEvent) {
+        self.events.push_back(event)
+    }
+}
+
+impl EventSource for ScriptedEventSource {
+    fn next_event(&mut self, _timeout: Duration) -> io::Result<Option<InputEvent>> {
+        Ok(self.events.pop_front())
+    }
+
+    fn is_closed(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+
+/// What wakes up the run loop of a `Window`
+pub(crate) enum LoopMessage {
+    Event(InputEvent),
+    Redraw,
+    Closed,
+    Error(io::Error),
+}
+
+/// How long the event pump waits for an event before checking if the loop ended
+const PUMP_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Moves the events of a source into the run loop channel, until the source is
+/// closed or the loop stops
+pub(crate) fn pump_events<S: EventSource>(
+    events: &mut S,
+    tx: SyncSender<LoopMessage>,
+    stop: &AtomicBool,
+) {
+    while !stop.load(Ordering::Relaxed) {
+        if events.is_closed() {
+            let _ = tx.send(LoopMessage::Closed);
+            return;
+        }
+        let message = match events.next_event(PUMP_INTERVAL) {
+            Ok(Some(ev)) => LoopMessage::Event(ev),
+            Ok(None) => continue,
+            Err(e) => LoopMessage::Error(e),
+        };
+        let is_error = matches!(message, LoopMessage::Error(_));
+        if tx.send(message).is_err() || is_error {
+            return;
+        }
+    }
+}
+
+thread_local! {
+    static CURRENT_REDRAW: RefCell<Option<RedrawHandle>> = const { RefCell::new(None) };
+    static CURRENT_COMPONENT: RefCell<Option<RenderId>> = const { RefCell::new(None) };
+}
+
+/// The component drawing or handling an event on this thread, for the hooks
+/// that need to send events back to it
+pub(crate) fn current_component() -> Option<RenderId> {
+    CURRENT_COMPONENT.with(|current| *current.borrow())
+}
+
+/// Makes `id` the `current_component` until the guard is dropped
+pub(crate) fn enter_component(id: RenderId) -> ComponentGuard {
+    let previous = CURRENT_COMPONENT.with(|current| current.replace(Some(id)));
+    ComponentGuard { previous }
+}
+
+pub(crate) struct ComponentGuard {
+    previous: Option<RenderId>,
+}
+
+impl Drop for ComponentGuard {
+    fn drop(&mut self) {
+        CURRENT_COMPONENT.with(|current| *current.borrow_mut() = self.previous);
+    }
+}
+
+#[derive(Default)]
+struct RedrawState {
+    dirty: AtomicBool,
+    waker: Mutex<Option<SyncSender<LoopMessage>>>,
+    ticks: Mutex<Vec<(RenderId, TimerId)>>,
+}
+
+/// Asks the run loop of a window for a new frame. It can be cloned and sent to
+/// other threads; requests made before the next frame are merged into one.
+#[derive(Clone, Default)]
+pub struct RedrawHandle {
+    state: Arc<RedrawState>,
+}
+
+impl RedrawHandle {
+    /// The handle of the window currently handling events or drawing on this
+    /// thread, so that components can take it with them
+    pub fn current() -> Option<RedrawHandle> {
+        CURRENT_REDRAW.with(|current| current.borrow().clone())
+    }
+
+    pub fn request_redraw(&self) {
+        if !self.state.dirty.swap(true, Ordering::SeqCst) {
+            if let Ok(waker) = self.state.waker.lock() {
+                if let Some(waker) = waker.as_ref() {
+                    // a full channel already wakes the loop up
+                    let _ = waker.try_send(LoopMessage::Redraw);
+                }
+            }
+        }
+    }
+
+    pub fn is_redraw_requested(&self) -> bool {
+        self.state.dirty.load(Ordering::SeqCst)
+    }
+
+    /// Clears the pending request, returning whether there was one
+    pub(crate) fn take_request(&self) -> bool {
+        self.state.dirty.swap(false, Ordering::SeqCst)
+    }
+
+    /// Queues a tick for the component owning a timer, waking the loop up.
+    /// Ticks that weren't handled yet aren't queued twice.
+    pub(crate) fn push_tick(&self, target: RenderId, timer: TimerId) {
+        if let Ok(mut ticks) = self.state.ticks.lock() {
+            if !ticks.contains(&(target, timer)) {
+                ticks.push((target, timer));
+            }
+        }
+        self.request_redraw()
+    }
+
+    pub(crate) fn take_ticks(&self) -> Vec<(RenderId, TimerId)> {
+        self.state
+            .ticks
+            .lock()
+            .map(|mut ticks| std::mem::take(&mut *ticks))
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn set_waker(&self, waker: Option<SyncSender<LoopMessage>>) {
+        if let Ok(mut current) = self.state.waker.lock() {
+            *current = waker;
+        }
+    }
+
+    /// Makes this the `current` handle until the guard is dropped
+    pub(crate) fn enter(&self) -> RedrawGuard {
+        let previous = CURRENT_REDRAW.with(|current| current.replace(Some(self.clone())));
+        RedrawGuard { previous }
+    }
+}
+
+pub(crate) struct RedrawGuard {
+    previous: Option<RedrawHandle>,
+}
+
+impl Drop for RedrawGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        CURRENT_REDRAW.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{thread, time::Duration};
+
+    use crossterm::event::{self, Event};
+    use ratatui::layout::Position;
+
+    use super::{
+        ChannelEventSource, DefaultEventMapper, EventMapper, EventSource, LoopMessage,
+        RedrawHandle, ScriptedEventSource,
+    };
+    use crate::core::{InputEvent, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, ScrollDirection};
+
+    fn key(code: event::KeyCode, modifiers: event::KeyModifiers) -> Event {
+        Event::Key(event::KeyEvent::new(code, modifiers))
+    }
+
+    #[test]
+    fn test_map_key_events() {
+        let ctrl_c = DefaultEventMapper::to_input_event(&key(
+            event::KeyCode::Char('c'),
+            event::KeyModifiers::CONTROL,
+        ));
+        assert!(matches!(
+            ctrl_c,
+            Some(InputEvent::Key(KeyEvent {
+                code: KeyCode::Char('c'),
+                modifiers: KeyModifiers::CONTROL,
+                kind: KeyEventKind::Press,
+            }))
+        ));
+        assert_eq!(ctrl_c.unwrap().as_char(), None);
+
+        let enter = DefaultEventMapper::to_input_event(&key(
+            event::KeyCode::Enter,
+            event::KeyModifiers::NONE,
+        ));
+        assert!(matches!(
+            enter,
+            Some(InputEvent::Key(KeyEvent {
+                code: KeyCode::Enter,
+                ..
+            }))
+        ));
+
+        let f5 = DefaultEventMapper::to_input_event(&key(
+            event::KeyCode::F(5),
+            event::KeyModifiers::SHIFT | event::KeyModifiers::ALT,
+        ));
+        assert!(matches!(
+            f5,
+            Some(InputEvent::Key(KeyEvent { code: KeyCode::F(5), modifiers, .. }))
+                if modifiers == KeyModifiers::SHIFT | KeyModifiers::ALT
+        ));
+
+        let upper_a = DefaultEventMapper::to_input_event(&key(
+            event::KeyCode::Char('A'),
+            event::KeyModifiers::SHIFT,
+        ));
+        assert_eq!(upper_a.unwrap().as_char(), Some('A'));
+    }
+
+    #[test]
+    fn test_map_focus_keys() {
+        assert!(matches!(
+            DefaultEventMapper::to_input_event(&key(
+                event::KeyCode::Tab,
+                event::KeyModifiers::NONE
+            )),
+            Some(InputEvent::FocusNext)
+        ));
+        assert!(matches!(
+            DefaultEventMapper::to_input_event(&key(
+                event::KeyCode::BackTab,
+                event::KeyModifiers::SHIFT
+            )),
+            Some(InputEvent::FocusPrevious)
+        ));
+        assert!(matches!(
+            DefaultEventMapper::to_input_event(&key(
+                event::KeyCode::Esc,
+                event::KeyModifiers::NONE
+            )),
+            Some(InputEvent::FocusWindow)
+        ));
+
+        let tab_release = Event::Key(event::KeyEvent::new_with_kind(
+            event::KeyCode::Tab,
+            event::KeyModifiers::NONE,
+            event::KeyEventKind::Release,
+        ));
+        assert!(matches!(
+            DefaultEventMapper::to_input_event(&tab_release),
+            Some(InputEvent::Key(KeyEvent {
+                code: KeyCode::Tab,
+                kind: KeyEventKind::Release,
+                ..
+            }))
+        ));
+    }
+
+    #[test]
+    fn test_map_mouse_paste_and_resize() {
+        let mouse = |kind| {
+            DefaultEventMapper::to_input_event(&Event::Mouse(event::MouseEvent {
+                kind,
+                column: 3,
+                row: 4,
+                modifiers: event::KeyModifiers::NONE,
+            }))
+        };
+        let position = Position::new(3, 4);
+
+        assert!(matches!(
+            mouse(event::MouseEventKind::ScrollDown),
+            Some(InputEvent::Scroll(ScrollDirection::Down, p)) if p == position
+        ));
+        assert!(matches!(
+            mouse(event::MouseEventKind::Drag(event::MouseButton::Left)),
+            Some(InputEvent::Drag(p)) if p == position
+        ));
+        assert!(matches!(
+            mouse(event::MouseEventKind::Down(event::MouseButton::Left)),
+            Some(InputEvent::MouseDown(p)) if p == position
+        ));
+        assert!(matches!(
+            mouse(event::MouseEventKind::Moved),
+            Some(InputEvent::MouseMove(p)) if p == position
+        ));
+        assert!(matches!(
+            DefaultEventMapper::to_input_event(&Event::Paste("pasted".into())),
+            Some(InputEvent::Paste(text)) if text == "pasted"
+        ));
+        assert!(matches!(
+            DefaultEventMapper::to_input_event(&Event::Resize(80, 24)),
+            Some(InputEvent::Resize(80, 24))
+        ));
+    }
+
+    #[test]
+    fn test_scripted_source() {
+        let mut source =
+            ScriptedEventSource::new(vec![InputEvent::FocusNext, InputEvent::Key('a'.into())]);
+        source.push(InputEvent::FocusWindow);
+
+        assert!(matches!(
+            source.next_event(Duration::ZERO).unwrap(),
+            Some(InputEvent::FocusNext)
+        ));
+        assert_eq!(
+            source
+                .next_event(Duration::ZERO)
+                .unwrap()
+                .unwrap()
+                .as_char(),
+            Some('a')
+        );
+        assert!(!source.is_closed());
+        assert!(matches!(
+            source.next_event(Duration::ZERO).unwrap(),
+            Some(InputEvent::FocusWindow)
+        ));
+        assert!(source.is_closed());
+        assert!(source.next_event(Duration::ZERO).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_channel_source() {
+        let (tx, mut source) = ChannelEventSource::channel();
+
+        assert!(source
+            .next_event(Duration::from_millis(1))
+            .unwrap()
+            .is_none());
+        assert!(!source.is_closed());
+
+        thread::spawn(move || tx.send(InputEvent::FocusNext).unwrap());
+        assert!(matches!(
+            source.next_event(Duration::from_secs(5)).unwrap(),
+            Some(InputEvent::FocusNext)
+        ));
+
+        assert!(source.next_event(Duration::from_secs(5)).unwrap().is_none());
+        assert!(source.is_closed());
+    }
+
+    #[test]
+    fn test_redraw_requests_are_merged() {
+        let handle = RedrawHandle::default();
+        let (tx, rx) = std::sync::mpsc::sync_channel(4);
+        handle.set_waker(Some(tx));
+
+        let other = handle.clone();
+        thread::spawn(move || {
+            other.request_redraw();
+            other.request_redraw();
+        })
+        .join()
+        .unwrap();
+
+        assert!(matches!(rx.try_recv(), Ok(LoopMessage::Redraw)));
+        assert!(rx.try_recv().is_err());
+        assert!(handle.take_request());
+        assert!(!handle.is_redraw_requested());
+
+        handle.request_redraw();
+        assert!(matches!(rx.try_recv(), Ok(LoopMessage::Redraw)));
+    }
+
+    #[test]
+    fn test_current_redraw_handle() {
+        assert!(RedrawHandle::current().is_none());
+        let handle = RedrawHandle::default();
+        {
+            let _guard = handle.enter();
+            RedrawHandle::current().unwrap().request_redraw();
+        }
+        assert!(RedrawHandle::current().is_none());
+        assert!(handle.is_redraw_requested());
+    }
+}
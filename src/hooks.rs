@@ -1,45 +1,1317 @@
 use std::{
-    sync::mpsc::{self, Receiver, Sender},
-    thread::{self, JoinHandle},
+    collections::VecDeque,
+    convert::Infallible,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc::{self, Receiver, Sender, TryRecvError},
+        Arc, Condvar, Mutex, OnceLock, Weak,
+    },
+    thread,
+    time::{Duration, Instant},
 };
 
-pub struct AsyncResource<T: Send> {
-    task: Option<JoinHandle<()>>,
-    channels: (Sender<T>, Receiver<T>),
-    result: Option<T>,
+use crate::{
+    core::{InputEvent, RenderId},
+    events::{current_component, RedrawHandle},
+};
+
+/// Tells a background task that its result is no longer wanted. Threads can't be
+/// stopped from the outside, so long tasks should check it now and then.
+#[derive(Clone, Default, Debug)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed)
+    }
+}
+
+/// What an `AsyncResource` has to show
+#[derive(Debug, PartialEq)]
+pub enum ResourceState<'a, T, E> {
+    /// No task was started yet
+    Idle,
+    /// A task is running, `previous` is the value of the last successful one
+    Loading {
+        previous: Option<&'a T>,
+    },
+    Ready(&'a T),
+    /// The last task failed, `previous` is the value of the last successful one
+    Failed {
+        error: &'a E,
+        previous: Option<&'a T>,
+    },
+    /// The last task panicked, `previous` is the value of the last successful one
+    Panicked {
+        previous: Option<&'a T>,
+    },
+}
+
+impl<'a, T, E> ResourceState<'a, T, E> {
+    /// The most recent value, even if it is being refreshed or the refresh failed
+    pub fn value(&self) -> Option<&'a T> {
+        match self {
+            ResourceState::Idle => None,
+            ResourceState::Loading { previous } => *previous,
+            ResourceState::Ready(value) => Some(value),
+            ResourceState::Failed { previous, .. } => *previous,
+            ResourceState::Panicked { previous } => *previous,
+        }
+    }
+
+    pub fn is_loading(&self) -> bool {
+        matches!(self, ResourceState::Loading { .. })
+    }
+}
+
+struct RunningTask<T, E> {
+    receiver: Receiver<Result<T, E>>,
+    cancel: CancelToken,
+    #[cfg(feature = "tokio")]
+    abort: Option<tokio::task::AbortHandle>,
+}
+
+impl<T, E> Drop for RunningTask<T, E> {
+    fn drop(&mut self) {
+        self.cancel.cancel();
+        #[cfg(feature = "tokio")]
+        if let Some(abort) = &self.abort {
+            abort.abort()
+        }
+    }
 }
 
-impl<'a, T: Send + 'a> AsyncResource<T> {
+/// A value computed on a background thread, started the first time a widget
+/// asks for it. Dropping the resource cancels the running task.
+pub struct AsyncResource<T: Send, E: Send = Infallible> {
+    task: Option<RunningTask<T, E>>,
+    needs_run: bool,
+    value: Option<T>,
+    error: Option<E>,
+    panicked: bool,
+}
+
+impl<T: Send, E: Send> AsyncResource<T, E> {
     pub fn new() -> Self {
         Self {
             task: None,
-            channels: mpsc::channel(),
-            result: None,
+            needs_run: true,
+            value: None,
+            error: None,
+            panicked: false,
         }
     }
 
+    /// Runs `task` on a new thread the first time it is called, and returns its
+    /// result once it is done. When called while a window draws or handles
+    /// events, the window is asked to redraw as soon as the result is ready.
     pub fn with_thread_spawning<F>(&mut self, task: F) -> &Option<T>
     where
         F: Send + 'static + FnOnce() -> T,
         T: Send + 'static,
+        E: Send + 'static,
     {
-        let (tx, rx) = &self.channels;
-        self.task.get_or_insert_with(|| {
-            let thread_tx = tx.clone();
-            thread::spawn(move || {
-                thread_tx.send(task()).unwrap();
+        self.load(move |_| Ok(task()));
+        &self.value
+    }
+
+    /// Like `with_thread_spawning` for tasks that can fail. The task runs again
+    /// only after a `refresh`, e.g. to retry a failed load.
+    pub fn load<F>(&mut self, task: F) -> ResourceState<'_, T, E>
+    where
+        F: Send + 'static + FnOnce(&CancelToken) -> Result<T, E>,
+        T: Send + 'static,
+        E: Send + 'static,
+    {
+        if self.needs_run {
+            self.needs_run = false;
+            self.spawn(task);
+        }
+        self.state()
+    }
+
+    /// Like `load`, running the future returned by `make_future` on a Tokio
+    /// runtime instead of a new thread. Dropping the resource or refreshing it
+    /// aborts the future.
+    #[cfg(feature = "tokio")]
+    pub fn load_future<F, Fut>(
+        &mut self,
+        runtime: &tokio::runtime::Handle,
+        make_future: F,
+    ) -> ResourceState<'_, T, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: std::future::Future<Output = Result<T, E>> + Send + 'static,
+        T: Send + 'static,
+        E: Send + 'static,
+    {
+        if self.needs_run {
+            self.needs_run = false;
+            let (tx, receiver) = mpsc::channel();
+            let redraw = RedrawHandle::current();
+            let future = make_future();
+            let handle = runtime.spawn(async move {
+                if tx.send(future.await).is_ok() {
+                    if let Some(redraw) = redraw {
+                        redraw.request_redraw()
+                    }
+                }
+            });
+            self.task = Some(RunningTask {
+                receiver,
+                cancel: CancelToken::default(),
+                abort: Some(handle.abort_handle()),
+            });
+        }
+        self.state()
+    }
+
+    /// The current state, taking the result of the running task if it is done
+    pub fn state(&mut self) -> ResourceState<'_, T, E> {
+        self.poll();
+        if self.task.is_some() {
+            ResourceState::Loading {
+                previous: self.value.as_ref(),
+            }
+        } else if self.panicked {
+            ResourceState::Panicked {
+                previous: self.value.as_ref(),
+            }
+        } else if let Some(error) = &self.error {
+            ResourceState::Failed {
+                error,
+                previous: self.value.as_ref(),
+            }
+        } else if let Some(value) = &self.value {
+            ResourceState::Ready(value)
+        } else {
+            ResourceState::Idle
+        }
+    }
+
+    /// Cancels the running task, if any, and runs the task again the next time
+    /// it is loaded. The current value stays available meanwhile.
+    pub fn refresh(&mut self) {
+        self.cancel();
+        self.needs_run = true;
+    }
+
+    /// Cancels the running task, keeping the last result, including the one of
+    /// a task that is done but wasn't read yet
+    pub fn cancel(&mut self) {
+        self.poll();
+        self.task = None;
+    }
+
+    fn spawn<F>(&mut self, task: F)
+    where
+        F: Send + 'static + FnOnce(&CancelToken) -> Result<T, E>,
+        T: Send + 'static,
+        E: Send + 'static,
+    {
+        let (tx, receiver) = mpsc::channel();
+        let cancel = CancelToken::default();
+        let thread_cancel = cancel.clone();
+        let redraw = RedrawHandle::current();
+        thread::spawn(move || {
+            let result = task(&thread_cancel);
+            // nobody is waiting for a cancelled task
+            if !thread_cancel.is_cancelled() && tx.send(result).is_ok() {
+                if let Some(redraw) = redraw {
+                    redraw.request_redraw()
+                }
+            }
+        });
+        self.task = Some(RunningTask {
+            receiver,
+            cancel,
+            #[cfg(feature = "tokio")]
+            abort: None,
+        });
+    }
+
+    fn poll(&mut self) {
+        let Some(task) = &self.task else {
+            return;
+        };
+        match task.receiver.try_recv() {
+            Ok(Ok(value)) => {
+                self.value = Some(value);
+                self.error = None;
+                self.panicked = false;
+            }
+            Ok(Err(error)) => {
+                self.error = Some(error);
+                self.panicked = false;
+            }
+            Err(TryRecvError::Empty) => return,
+            // the task panicked, keep the last result
+            Err(TryRecvError::Disconnected) => self.panicked = true,
+        }
+        self.task = None;
+    }
+}
+
+impl<T: Send, E: Send> Default for AsyncResource<T, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Hands the values of a producer to its `StreamResource`
+pub struct StreamSender<T> {
+    tx: Sender<T>,
+    cancel: CancelToken,
+    redraw: Option<RedrawHandle>,
+}
+
+impl<T> StreamSender<T> {
+    /// Sends a value, asking the window for a redraw. Returns `false` once the
+    /// resource is gone or was restarted, the producer should stop then.
+    pub fn send(&self, value: T) -> bool {
+        if self.is_cancelled() || self.tx.send(value).is_err() {
+            return false;
+        }
+        if let Some(redraw) = &self.redraw {
+            redraw.request_redraw()
+        }
+        true
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+}
+
+struct RunningStream<T> {
+    receiver: Receiver<T>,
+    cancel: CancelToken,
+}
+
+impl<T> Drop for RunningStream<T> {
+    fn drop(&mut self) {
+        self.cancel.cancel()
+    }
+}
+
+/// Values produced over time on a background thread (log lines, progress,
+/// search hits...). Every call returns all the values received so far, or only
+/// the most recent ones when created `with_capacity`.
+pub struct StreamResource<T: Send> {
+    task: Option<RunningStream<T>>,
+    needs_run: bool,
+    items: VecDeque<T>,
+    capacity: Option<usize>,
+}
+
+impl<T: Send> StreamResource<T> {
+    pub fn new() -> Self {
+        Self {
+            task: None,
+            needs_run: true,
+            items: VecDeque::new(),
+            capacity: None,
+        }
+    }
+
+    /// Keeps only the last `capacity` values
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            capacity: Some(capacity),
+            ..Self::new()
+        }
+    }
+
+    /// Runs `producer` on a new thread the first time it is called, and returns
+    /// the values it sent so far
+    pub fn with_thread_spawning<F>(&mut self, producer: F) -> &VecDeque<T>
+    where
+        F: Send + 'static + FnOnce(StreamSender<T>),
+        T: 'static,
+    {
+        if self.needs_run {
+            self.needs_run = false;
+            self.spawn(producer);
+        }
+        self.items()
+    }
+
+    /// The values received so far, without starting the producer
+    pub fn items(&mut self) -> &VecDeque<T> {
+        self.poll();
+        &self.items
+    }
+
+    /// Whether the producer is still sending values
+    pub fn is_running(&self) -> bool {
+        self.task.is_some()
+    }
+
+    /// Stops the producer and runs it again the next time it is called,
+    /// starting from an empty list
+    pub fn restart(&mut self) {
+        self.cancel();
+        self.items.clear();
+        self.needs_run = true;
+    }
+
+    /// Stops the producer, keeping the values received so far
+    pub fn cancel(&mut self) {
+        self.poll();
+        self.task = None;
+    }
+
+    fn spawn<F>(&mut self, producer: F)
+    where
+        F: Send + 'static + FnOnce(StreamSender<T>),
+        T: 'static,
+    {
+        let (tx, receiver) = mpsc::channel();
+        let cancel = CancelToken::default();
+        let sender = StreamSender {
+            tx,
+            cancel: cancel.clone(),
+            redraw: RedrawHandle::current(),
+        };
+        thread::spawn(move || producer(sender));
+        self.task = Some(RunningStream { receiver, cancel });
+    }
+
+    fn poll(&mut self) {
+        let Some(task) = &self.task else {
+            return;
+        };
+        loop {
+            match task.receiver.try_recv() {
+                Ok(item) => {
+                    self.items.push_back(item);
+                    if self.capacity.is_some_and(|c| self.items.len() > c) {
+                        self.items.pop_front();
+                    }
+                }
+                Err(TryRecvError::Empty) => return,
+                Err(TryRecvError::Disconnected) => break,
+            }
+        }
+        self.task = None;
+    }
+}
+
+impl<T: Send> Default for StreamResource<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Tells the ticks of the timers of a component apart
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimerId(u64);
+
+impl TimerId {
+    fn new() -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        Self(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// Sends `InputEvent::Tick` to the component owning it, once after a delay or
+/// at a fixed interval, whether it is focused or not. The window redraws after
+/// every tick. All the timers wait on one thread, started with the first one,
+/// and each measures time with its `Clock`.
+pub struct Timer<C: Clock = SystemClock> {
+    id: TimerId,
+    period: Duration,
+    repeat: bool,
+    clock: C,
+    started: bool,
+    slot: Option<Arc<TimerSlot>>,
+}
+
+impl Timer {
+    pub fn interval(period: Duration) -> Self {
+        Self {
+            id: TimerId::new(),
+            period,
+            repeat: true,
+            clock: SystemClock,
+            started: false,
+            slot: None,
+        }
+    }
+
+    pub fn once(delay: Duration) -> Self {
+        let mut timer = Self::interval(delay);
+        timer.repeat = false;
+        timer
+    }
+}
+
+impl<C: Clock> Timer<C> {
+    /// Measures the time with `clock` rather than the system time. Has to be
+    /// called before the timer is started.
+    pub fn with_clock<C2: Clock>(self, clock: C2) -> Timer<C2> {
+        Timer {
+            id: self.id,
+            period: self.period,
+            repeat: self.repeat,
+            clock,
+            started: false,
+            slot: None,
+        }
+    }
+
+    pub fn id(&self) -> TimerId {
+        self.id
+    }
+
+    /// Whether the event is a tick of this timer
+    pub fn is_tick(&self, event: &InputEvent) -> bool {
+        matches!(event, InputEvent::Tick(id) if *id == self.id)
+    }
+
+    /// Whether the timer was started and not stopped. One-shot timers stay
+    /// started after firing, until `restart`.
+    pub fn is_started(&self) -> bool {
+        self.started
+    }
+
+    /// Starts the timer for the component being drawn or handling an event,
+    /// unless it is already started. Returns whether it is started.
+    ///
+    /// The first time, the timer learns which component to send its ticks to.
+    /// Outside of `render` or `on_event`, with no window drawing or handling
+    /// events on this thread, there is none: the timer stays stopped and
+    /// `false` is returned, until it is started from there.
+    pub fn start(&mut self) -> bool {
+        if !self.started {
+            self.started = self.schedule(self.period);
+        }
+        self.started
+    }
+
+    /// Starts the timer over, e.g. to fire a one-shot timer again. Like
+    /// `start`, returns `false` if the timer can't know its component yet.
+    pub fn restart(&mut self) -> bool {
+        self.restart_in(self.period)
+    }
+
+    /// Like `restart`, firing first after `delay` instead of the period
+    pub fn restart_in(&mut self, delay: Duration) -> bool {
+        self.started = self.schedule(delay);
+        self.started
+    }
+
+    pub fn stop(&mut self) {
+        self.started = false;
+        if self.slot.is_some() {
+            scheduler().set_deadline(self.id, None)
+        }
+    }
+
+    fn schedule(&mut self, delay: Duration) -> bool {
+        if self.slot.is_none() {
+            let (Some(target), Some(redraw)) = (current_component(), RedrawHandle::current())
+            else {
+                return false;
+            };
+            self.slot = Some(self.register(target, redraw));
+        }
+        scheduler().set_deadline(self.id, Some(self.clock.now() + delay));
+        true
+    }
+
+    fn register(&self, target: RenderId, redraw: RedrawHandle) -> Arc<TimerSlot> {
+        let slot = Arc::new(TimerSlot { id: self.id });
+        let wakes_up = self.clock.on_change(ClockWaker {
+            timer: Arc::downgrade(&slot),
+        });
+        let clock = self.clock.clone();
+        scheduler().update(|timers| {
+            timers.push(ScheduledTimer {
+                id: self.id,
+                target,
+                redraw,
+                period: self.period,
+                repeat: self.repeat,
+                now: Box::new(move || clock.now()),
+                wakes_up,
+                deadline: None,
             })
         });
+        slot
+    }
+}
+
+/// Keeps a timer on the scheduler until the `Timer` is dropped
+struct TimerSlot {
+    id: TimerId,
+}
+
+impl Drop for TimerSlot {
+    fn drop(&mut self) {
+        let id = self.id;
+        scheduler().update(|timers| timers.retain(|timer| timer.id != id))
+    }
+}
 
-        if let Ok(res) = rx.try_recv() {
-            self.result = Some(res)
+/// A started timer, as seen from the scheduler thread
+struct ScheduledTimer {
+    id: TimerId,
+    target: RenderId,
+    redraw: RedrawHandle,
+    period: Duration,
+    repeat: bool,
+    now: Box<dyn Fn() -> Instant + Send>,
+    /// Whether the clock wakes the scheduler up when it moves
+    wakes_up: bool,
+    deadline: Option<Instant>,
+}
+
+/// The thread all the timers wait on
+struct Scheduler {
+    timers: Mutex<Vec<ScheduledTimer>>,
+    changed: Condvar,
+}
+
+/// The scheduler, started with the first timer. Its thread lives as long as
+/// the process, sleeping while no timer is due.
+fn scheduler() -> &'static Scheduler {
+    static SCHEDULER: OnceLock<Scheduler> = OnceLock::new();
+    let mut is_new = false;
+    let scheduler = SCHEDULER.get_or_init(|| {
+        is_new = true;
+        Scheduler {
+            timers: Mutex::default(),
+            changed: Condvar::new(),
         }
-        &self.result
+    });
+    if is_new {
+        thread::spawn(move || scheduler.run());
     }
+    scheduler
 }
 
-impl<T: Send + 'static> Default for AsyncResource<T> {
+impl Scheduler {
+    fn set_deadline(&self, id: TimerId, deadline: Option<Instant>) {
+        self.update(|timers| {
+            if let Some(timer) = timers.iter_mut().find(|timer| timer.id == id) {
+                timer.deadline = deadline
+            }
+        })
+    }
+
+    fn update(&self, f: impl FnOnce(&mut Vec<ScheduledTimer>)) {
+        if let Ok(mut timers) = self.timers.lock() {
+            f(&mut timers);
+        }
+        self.changed.notify_all();
+    }
+
+    fn run(&self) {
+        let Ok(mut timers) = self.timers.lock() else {
+            return;
+        };
+        loop {
+            let mut ticks = vec![];
+            let mut timeout: Option<Duration> = None;
+            for timer in timers.iter_mut() {
+                let Some(deadline) = timer.deadline else {
+                    continue;
+                };
+                let now = (timer.now)();
+                if now >= deadline {
+                    timer.deadline = timer.repeat.then_some(deadline + timer.period);
+                    ticks.push((timer.redraw.clone(), timer.target, timer.id));
+                } else if !timer.wakes_up {
+                    let left = deadline - now;
+                    timeout = Some(timeout.map_or(left, |timeout| timeout.min(left)));
+                }
+            }
+            let waited = if !ticks.is_empty() {
+                drop(timers);
+                for (redraw, target, id) in ticks {
+                    redraw.push_tick(target, id)
+                }
+                self.timers.lock().ok()
+            } else if let Some(timeout) = timeout {
+                self.changed
+                    .wait_timeout(timers, timeout)
+                    .map(|(timers, _)| timers)
+                    .ok()
+            } else {
+                // the clocks of the timers wake the scheduler up when they move
+                self.changed.wait(timers).ok()
+            };
+            let Some(next) = waited else {
+                return;
+            };
+            timers = next;
+        }
+    }
+}
+
+/// Wakes the timers up to check the time of their clock again, see
+/// `Clock::on_change`
+pub struct ClockWaker {
+    timer: Weak<TimerSlot>,
+}
+
+impl ClockWaker {
+    /// Returns `false` once the timer is gone
+    pub fn wake(&self) -> bool {
+        match self.timer.upgrade() {
+            Some(_) => {
+                scheduler().update(|_| {});
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Where timers, `Debounced` and `Throttled` read the time from
+pub trait Clock: Clone + Send + 'static {
+    fn now(&self) -> Instant;
+
+    /// Called once by each timer measuring time with this clock. Clocks that
+    /// don't follow the system time keep `waker` to call it whenever they move,
+    /// and return `true`: their timers sleep until then.
+    fn on_change(&self, waker: ClockWaker) -> bool {
+        let _ = waker;
+        false
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A clock that only moves when told to, for tests. Clones share their time,
+/// and the timers using it fire when it is advanced past their deadline.
+#[derive(Clone)]
+pub struct ManualClock {
+    now: Arc<Mutex<Instant>>,
+    wakers: Arc<Mutex<Vec<ClockWaker>>>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self {
+            now: Arc::new(Mutex::new(Instant::now())),
+            wakers: Arc::default(),
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        if let Ok(mut now) = self.now.lock() {
+            *now += duration
+        }
+        if let Ok(mut wakers) = self.wakers.lock() {
+            wakers.retain(|waker| waker.wake());
+        }
+    }
+}
+
+impl Default for ManualClock {
     fn default() -> Self {
         Self::new()
     }
 }
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.now
+            .lock()
+            .map(|now| *now)
+            .unwrap_or_else(|_| Instant::now())
+    }
+
+    fn on_change(&self, waker: ClockWaker) -> bool {
+        if let Ok(mut wakers) = self.wakers.lock() {
+            wakers.push(waker);
+        }
+        true
+    }
+}
+
+/// Holds the latest value until it hasn't changed for `delay`, e.g. to search
+/// only once the user stops typing. Inside a window, a tick is sent to the
+/// component when the value is ready (see `is_tick`).
+pub struct Debounced<T, C: Clock = SystemClock> {
+    delay: Duration,
+    clock: C,
+    pending: Option<(T, Instant)>,
+    timer: Timer<C>,
+}
+
+impl<T> Debounced<T> {
+    pub fn new(delay: Duration) -> Self {
+        Self::with_clock(delay, SystemClock)
+    }
+}
+
+impl<T, C: Clock> Debounced<T, C> {
+    pub fn with_clock(delay: Duration, clock: C) -> Self {
+        Self {
+            delay,
+            clock: clock.clone(),
+            pending: None,
+            timer: Timer::once(delay).with_clock(clock),
+        }
+    }
+
+    /// Replaces the pending value, starting the delay over
+    pub fn set(&mut self, value: T) {
+        self.pending = Some((value, self.clock.now()));
+        self.timer.restart();
+    }
+
+    /// The pending value, once it has been left alone for the whole delay
+    pub fn take(&mut self) -> Option<T> {
+        let (_, changed) = self.pending.as_ref()?;
+        if self.clock.now().duration_since(*changed) < self.delay {
+            return None;
+        }
+        self.timer.stop();
+        self.pending.take().map(|(value, _)| value)
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Whether the event is the tick sent when the value is ready
+    pub fn is_tick(&self, event: &InputEvent) -> bool {
+        self.timer.is_tick(event)
+    }
+}
+
+/// Lets values through at most once per `period`: the first one right away, and
+/// the latest one given meanwhile at the end of the period. Inside a window, a
+/// tick is sent to the component when that one is ready (see `is_tick`).
+pub struct Throttled<T, C: Clock = SystemClock> {
+    period: Duration,
+    clock: C,
+    pending: Option<T>,
+    last_taken: Option<Instant>,
+    timer: Timer<C>,
+}
+
+impl<T> Throttled<T> {
+    pub fn new(period: Duration) -> Self {
+        Self::with_clock(period, SystemClock)
+    }
+}
+
+impl<T, C: Clock> Throttled<T, C> {
+    pub fn with_clock(period: Duration, clock: C) -> Self {
+        Self {
+            period,
+            clock: clock.clone(),
+            pending: None,
+            last_taken: None,
+            timer: Timer::once(period).with_clock(clock),
+        }
+    }
+
+    /// Replaces the pending value
+    pub fn set(&mut self, value: T) {
+        self.pending = Some(value);
+        if let Some(remaining) = self.remaining() {
+            if !self.timer.is_started() {
+                self.timer.restart_in(remaining);
+            }
+        }
+    }
+
+    /// The pending value, if none was taken during the last period
+    pub fn take(&mut self) -> Option<T> {
+        if self.pending.is_none() || self.remaining().is_some() {
+            return None;
+        }
+        self.last_taken = Some(self.clock.now());
+        self.timer.stop();
+        self.pending.take()
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Whether the event is the tick sent when the pending value is ready
+    pub fn is_tick(&self, event: &InputEvent) -> bool {
+        self.timer.is_tick(event)
+    }
+
+    /// How long until a value can be taken again
+    fn remaining(&self) -> Option<Duration> {
+        let elapsed = self.clock.now().duration_since(self.last_taken?);
+        self.period.checked_sub(elapsed).filter(|d| !d.is_zero())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc, sync::mpsc, thread, time::Duration};
+
+    use ratatui::{buffer::Buffer, layout::Rect};
+
+    use super::{
+        AsyncResource, Debounced, ManualClock, ResourceState, StreamResource, Throttled, Timer,
+        TimerId,
+    };
+    use crate::{
+        core::InputEvent,
+        events::RedrawHandle,
+        render::{EventContext, EventResult, FocusableRender, Render, RenderProps},
+        testing::TestDriver,
+        utils::wait_until,
+        windows::{page::Page, page_collection::PageCollection},
+    };
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    #[track_caller]
+    fn wait_until_loaded<T: Send, E: Send>(resource: &mut AsyncResource<T, E>) {
+        wait_until(|| !resource.state().is_loading(), TIMEOUT);
+    }
+
+    #[test]
+    fn test_completion_requests_redraw() {
+        let redraw = RedrawHandle::default();
+        let mut resource = AsyncResource::<_>::new();
+        {
+            let _guard = redraw.enter();
+            resource.with_thread_spawning(|| 42);
+        }
+
+        wait_until(|| redraw.is_redraw_requested(), TIMEOUT);
+        assert_eq!(resource.with_thread_spawning(|| 0), &Some(42));
+    }
+
+    #[test]
+    fn test_failure_and_refresh() {
+        let mut resource = AsyncResource::<u32, String>::new();
+        assert_eq!(resource.state(), ResourceState::Idle);
+
+        resource.load(|_| Err("offline".to_string()));
+        wait_until_loaded(&mut resource);
+        assert_eq!(
+            resource.load(|_| Ok(1)),
+            ResourceState::Failed {
+                error: &"offline".to_string(),
+                previous: None
+            }
+        );
+
+        resource.refresh();
+        resource.load(|_| Ok(1));
+        wait_until_loaded(&mut resource);
+        assert_eq!(resource.state(), ResourceState::Ready(&1));
+
+        // the previous value is kept while refreshing
+        let (tx, rx) = mpsc::channel();
+        resource.refresh();
+        assert_eq!(
+            resource.load(move |_| Ok(rx.recv().unwrap())),
+            ResourceState::Loading { previous: Some(&1) }
+        );
+        tx.send(2).unwrap();
+        wait_until_loaded(&mut resource);
+        assert_eq!(resource.state(), ResourceState::Ready(&2));
+    }
+
+    #[test]
+    fn test_panic() {
+        let mut resource = AsyncResource::<u32>::new();
+        resource.load(|_| panic!("the task panicked"));
+        wait_until_loaded(&mut resource);
+        assert_eq!(resource.state(), ResourceState::Panicked { previous: None });
+
+        resource.refresh();
+        resource.load(|_| Ok(1));
+        wait_until_loaded(&mut resource);
+        resource.refresh();
+        resource.load(|_| panic!("the task panicked"));
+        wait_until_loaded(&mut resource);
+        assert_eq!(
+            resource.state(),
+            ResourceState::Panicked { previous: Some(&1) }
+        );
+    }
+
+    #[test]
+    fn test_cancel_keeps_finished_result() {
+        let redraw = RedrawHandle::default();
+        let mut resource = AsyncResource::<u32>::new();
+        {
+            let _guard = redraw.enter();
+            resource.load(|_| Ok(1));
+        }
+        // the result was sent, but nobody read it yet
+        wait_until(|| redraw.is_redraw_requested(), TIMEOUT);
+        resource.cancel();
+        assert_eq!(resource.state(), ResourceState::Ready(&1));
+    }
+
+    #[test]
+    fn test_cancel() {
+        let (started_tx, started_rx) = mpsc::channel();
+        let (cancelled_tx, cancelled_rx) = mpsc::channel();
+        let mut resource = AsyncResource::<u32>::new();
+        resource.load(move |token| {
+            started_tx.send(()).unwrap();
+            while !token.is_cancelled() {
+                thread::sleep(Duration::from_millis(1));
+            }
+            cancelled_tx.send(()).unwrap();
+            Ok(1)
+        });
+        started_rx.recv_timeout(TIMEOUT).unwrap();
+
+        // refreshing replaces the running task
+        resource.refresh();
+        cancelled_rx.recv_timeout(TIMEOUT).unwrap();
+        resource.load(|_| Ok(2));
+        wait_until_loaded(&mut resource);
+        assert_eq!(resource.state(), ResourceState::Ready(&2));
+
+        // dropping the resource cancels its task
+        let (started_tx, started_rx) = mpsc::channel();
+        let (cancelled_tx, cancelled_rx) = mpsc::channel();
+        let mut resource = AsyncResource::<u32>::new();
+        resource.load(move |token| {
+            started_tx.send(()).unwrap();
+            while !token.is_cancelled() {
+                thread::sleep(Duration::from_millis(1));
+            }
+            cancelled_tx.send(()).unwrap();
+            Ok(1)
+        });
+        started_rx.recv_timeout(TIMEOUT).unwrap();
+        drop(resource);
+        cancelled_rx.recv_timeout(TIMEOUT).unwrap();
+    }
+
+    #[test]
+    fn test_stream() {
+        let redraw = RedrawHandle::default();
+        let (tx, rx) = mpsc::channel();
+        let mut stream = StreamResource::with_capacity(3);
+        {
+            let _guard = redraw.enter();
+            stream.with_thread_spawning(move |sender| {
+                for i in 0..5 {
+                    sender.send(i);
+                }
+                rx.recv().unwrap();
+                sender.send(5);
+            });
+        }
+
+        wait_until(
+            || stream.items().len() == 3 && stream.items().back() == Some(&4),
+            TIMEOUT,
+        );
+        assert!(redraw.is_redraw_requested());
+        assert!(stream.is_running());
+        assert_eq!(stream.items(), &[2, 3, 4]);
+
+        tx.send(()).unwrap();
+        wait_until(
+            || {
+                stream.items();
+                !stream.is_running()
+            },
+            TIMEOUT,
+        );
+        // the producer only runs once
+        assert_eq!(stream.with_thread_spawning(|_| unreachable!()), &[3, 4, 5]);
+    }
+
+    #[test]
+    fn test_stream_restart() {
+        let mut stream = StreamResource::new();
+        let (cancelled_tx, cancelled_rx) = mpsc::channel();
+        stream.with_thread_spawning(move |sender| {
+            let mut i = 0;
+            while sender.send(i) {
+                i += 1;
+                thread::sleep(Duration::from_millis(1));
+            }
+            cancelled_tx.send(()).unwrap();
+        });
+        wait_until(|| !stream.items().is_empty(), TIMEOUT);
+
+        stream.restart();
+        cancelled_rx.recv_timeout(TIMEOUT).unwrap();
+        assert!(stream.items().is_empty());
+
+        stream.with_thread_spawning(|sender| {
+            sender.send(10);
+        });
+        wait_until(
+            || {
+                stream.items();
+                !stream.is_running()
+            },
+            TIMEOUT,
+        );
+        assert_eq!(stream.items(), &[10]);
+    }
+
+    struct Clock {
+        interval: Timer,
+        once: Timer,
+        ticks: Rc<RefCell<(usize, usize)>>,
+    }
+
+    impl Render for Clock {
+        fn render(&mut self, _render_props: &RenderProps, _buff: &mut Buffer, _area: Rect) {
+            assert!(self.interval.start());
+            assert!(self.once.start());
+        }
+
+        fn on_event(&mut self, event: &InputEvent, _ctx: &mut EventContext) -> EventResult {
+            let mut ticks = self.ticks.borrow_mut();
+            if self.interval.is_tick(event) {
+                ticks.0 += 1;
+            } else if self.once.is_tick(event) {
+                ticks.1 += 1;
+            } else {
+                return EventResult::Ignored;
+            }
+            EventResult::Consumed
+        }
+    }
+
+    struct Input {}
+
+    impl FocusableRender for Input {
+        fn render(&mut self, _render_props: &RenderProps, _buff: &mut Buffer, _area: Rect) {}
+    }
+
+    #[test]
+    fn test_timers() {
+        let ticks = Rc::new(RefCell::new((0, 0)));
+        let clock = Clock {
+            interval: Timer::interval(Duration::from_millis(2)),
+            once: Timer::once(Duration::from_millis(1)),
+            ticks: ticks.clone(),
+        };
+        // a timer can't start outside of a window
+        assert!(!Timer::once(Duration::ZERO).start());
+
+        let app = PageCollection::new(vec![
+            Page::new("Clock", 'c', column_widget!(clock, Input {})),
+            Page::new("Other", 'o', Input {}),
+        ]);
+        let mut driver = TestDriver::new(app, 20, 5);
+        driver.send(InputEvent::FocusNext);
+
+        wait_until(
+            || {
+                driver.handle_timer_ticks();
+                ticks.borrow().0 >= 3
+            },
+            TIMEOUT,
+        );
+        // ticks keep coming on another page
+        driver.send_all([InputEvent::FocusWindow, InputEvent::Key('o'.into())]);
+        let before = ticks.borrow().0;
+        wait_until(
+            || {
+                driver.handle_timer_ticks();
+                ticks.borrow().0 > before
+            },
+            TIMEOUT,
+        );
+        assert_eq!(ticks.borrow().1, 1);
+    }
+
+    #[test]
+    fn test_debounce() {
+        let clock = ManualClock::new();
+        let mut search = Debounced::with_clock(Duration::from_millis(100), clock.clone());
+        assert_eq!(search.take(), None);
+
+        search.set("a");
+        clock.advance(Duration::from_millis(50));
+        search.set("ab");
+        clock.advance(Duration::from_millis(99));
+        assert_eq!(search.take(), None);
+        assert!(search.is_pending());
+
+        clock.advance(Duration::from_millis(1));
+        assert_eq!(search.take(), Some("ab"));
+        assert_eq!(search.take(), None);
+    }
+
+    #[test]
+    fn test_throttle() {
+        let clock = ManualClock::new();
+        let mut progress = Throttled::with_clock(Duration::from_millis(100), clock.clone());
+
+        progress.set(1);
+        assert_eq!(progress.take(), Some(1));
+        progress.set(2);
+        progress.set(3);
+        clock.advance(Duration::from_millis(60));
+        assert_eq!(progress.take(), None);
+
+        clock.advance(Duration::from_millis(40));
+        assert_eq!(progress.take(), Some(3));
+        assert_eq!(progress.take(), None);
+
+        // quiet periods don't hold the next value back
+        clock.advance(Duration::from_millis(500));
+        progress.set(4);
+        assert_eq!(progress.take(), Some(4));
+    }
+
+    #[derive(Default)]
+    struct SearchLog {
+        searches: Vec<String>,
+        lengths: Vec<usize>,
+        throttle_ticks: Vec<TimerId>,
+    }
+
+    struct Search {
+        query: Debounced<String, ManualClock>,
+        length: Throttled<usize, ManualClock>,
+        typed: String,
+        log: Rc<RefCell<SearchLog>>,
+    }
+
+    impl FocusableRender for Search {
+        fn render(&mut self, _render_props: &RenderProps, _buff: &mut Buffer, _area: Rect) {}
+
+        fn on_event(&mut self, event: &InputEvent, _ctx: &mut EventContext) -> EventResult {
+            let mut log = self.log.borrow_mut();
+            if let Some(c) = event.as_char() {
+                self.typed.push(c);
+                self.query.set(self.typed.clone());
+                self.length.set(self.typed.len());
+            } else if let InputEvent::Tick(id) = event {
+                if self.length.is_tick(event) {
+                    log.throttle_ticks.push(*id);
+                }
+            } else {
+                return EventResult::Ignored;
+            }
+            log.searches.extend(self.query.take());
+            log.lengths.extend(self.length.take());
+            EventResult::Consumed
+        }
+    }
+
+    /// Any tick takes both values, but the throttle ticks may come a bit later
+    #[track_caller]
+    fn wait_for_throttle_ticks(driver: &mut TestDriver, log: &RefCell<SearchLog>, count: usize) {
+        wait_until(
+            || {
+                driver.handle_timer_ticks();
+                log.borrow().throttle_ticks.len() >= count
+            },
+            TIMEOUT,
+        );
+    }
+
+    #[test]
+    fn test_debounce_and_throttle_in_window() {
+        let clock = ManualClock::new();
+        let log = Rc::new(RefCell::new(SearchLog::default()));
+        let search = Search {
+            query: Debounced::with_clock(Duration::from_millis(10), clock.clone()),
+            length: Throttled::with_clock(Duration::from_millis(10), clock.clone()),
+            typed: String::new(),
+            log: log.clone(),
+        };
+        let app = PageCollection::new(vec![Page::new("Search", 's', search)]);
+        let mut driver = TestDriver::new(app, 20, 5);
+        driver.send(InputEvent::FocusNext);
+
+        driver.type_text("abc");
+        assert_eq!(log.borrow().lengths, vec![1]);
+        // nothing fires until the clock moves
+        assert!(!driver.handle_timer_ticks());
+
+        clock.advance(Duration::from_millis(10));
+        wait_for_throttle_ticks(&mut driver, &log, 1);
+        assert_eq!(log.borrow().searches, vec!["abc"]);
+        assert_eq!(log.borrow().lengths, vec![1, 3]);
+
+        driver.type_text("d");
+        clock.advance(Duration::from_millis(10));
+        wait_for_throttle_ticks(&mut driver, &log, 2);
+        assert_eq!(log.borrow().searches, vec!["abc", "abcd"]);
+        assert_eq!(log.borrow().lengths, vec![1, 3, 4]);
+
+        // the throttle keeps a single timer
+        let ticks = &log.borrow().throttle_ticks;
+        assert_eq!(ticks[0], ticks[1]);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn test_load_future() {
+        use std::{future::poll_fn, task::Poll};
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let redraw = RedrawHandle::default();
+        let mut resource = AsyncResource::<u32, String>::new();
+
+        // a future that needs a few polls to complete
+        let mut polls = 0;
+        let future = poll_fn(move |cx| {
+            polls += 1;
+            if polls < 3 {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            } else {
+                Poll::Ready(Ok(polls))
+            }
+        });
+        {
+            let _guard = redraw.enter();
+            let state = resource.load_future(runtime.handle(), move || future);
+            assert!(state.is_loading());
+        }
+
+        // a current thread runtime only makes progress while blocked on
+        while resource.state().is_loading() {
+            runtime.block_on(tokio::task::yield_now());
+        }
+        assert_eq!(resource.state(), ResourceState::Ready(&3));
+        assert!(redraw.is_redraw_requested());
+
+        // refreshing aborts the pending future
+        resource.refresh();
+        resource.load_future(runtime.handle(), std::future::pending);
+        resource.refresh();
+        resource.load_future(runtime.handle(), || async { Err("offline".to_string()) });
+        while resource.state().is_loading() {
+            runtime.block_on(tokio::task::yield_now());
+        }
+        assert_eq!(
+            resource.state(),
+            ResourceState::Failed {
+                error: &"offline".to_string(),
+                previous: Some(&3)
+            }
+        );
+    }
+}
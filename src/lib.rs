@@ -2,9 +2,11 @@ pub mod core;
 #[macro_use]
 pub mod macros;
 pub mod api;
-pub(crate) mod area_calculation;
+pub mod events;
 pub mod hooks;
 pub mod render;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 pub mod tui;
 pub(crate) mod utils;
 pub mod windows;
@@ -12,10 +14,6 @@ pub mod windows;
 #[cfg(test)]
 mod tests {
 
-    use std::error::Error;
-    use std::thread;
-    use std::time::Duration;
-
     use ratatui::{
         buffer::Buffer,
         layout::Rect,
@@ -23,14 +21,10 @@ mod tests {
     };
 
     use crate::{
-        core::RenderComponent,
+        core::InputEvent,
         render::{FocusableRender, Render, RenderProps},
-        tui::TuiCrossterm,
-        windows::{
-            page::Page,
-            page_collection::PageCollection,
-            window::{DefaultEventMapper, Window},
-        },
+        testing::TestDriver,
+        windows::{page::Page, page_collection::PageCollection, window::Window},
     };
 
     struct TestWidget {}
@@ -54,32 +48,24 @@ mod tests {
     }
 
     #[test]
-    fn tui_test() -> Result<(), Box<dyn Error>> {
-        thread::sleep(Duration::from_millis(500));
-
-        let mut tui = TuiCrossterm::new()?;
-        let terminal = tui.setup()?;
-        // our stuff:
-        let mut app = PageCollection::new(vec![Page::new(
+    fn tui_test() {
+        let app = PageCollection::new(vec![Page::new(
             "Page",
             'p',
             row_widget!(TestWidget {}, StaticWidget {}),
         )]);
-        let mut window = Window::new(&app, |ev| match ev {
-            crate::core::InputEvent::Key(c) => *c == 'q',
-            _ => false,
-        });
-        terminal.draw(|f| {
-            let area = f.size();
-            let buff = f.buffer_mut();
+        let window = Window::new(&app, |ev| ev.as_char() == Some('q'));
+        let mut driver = TestDriver::with_window(app, window, 80, 10);
+
+        assert!(driver.contains_text("Hello world! Focused? false"));
+        assert!(driver.contains_text("I'm static"));
 
-            // draw
-            window.render::<DefaultEventMapper>(&mut app, buff, area)
-        })?;
-        thread::sleep(Duration::from_secs(1));
+        driver.send(InputEvent::FocusNext);
+        assert!(driver.focused().is_some());
+        assert!(driver.contains_text("Hello world! Focused? true"));
 
-        // TuiCrossterm::tear_down(terminal)
-        Ok(())
+        driver.send(InputEvent::FocusWindow).type_text("q");
+        assert!(driver.is_finished());
     }
 
     #[test]
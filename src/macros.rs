@@ -1,26 +1,49 @@
+/// Creates a column of widgets, as a `LayoutNode` to set its margin, padding,
+/// spacing or event handler. Each widget may be followed by `=> constraint`
+/// to control its size, e.g. `column_widget!(Header {} => Constraint::Length(3), Body {})`.
+/// Widgets without a constraint evenly share the remaining space.
+/// A key can be given instead of or along with the constraint, as in
+/// `Search {} => "search"` or `Search {} => (Constraint::Length(3), "search")`.
+/// The keys aren't checked here: build the page with `Page::try_new` (or the
+/// window with `Window::try_new`) to get an error when one is used twice.
 #[macro_export]
 macro_rules! column_widget {
-    ($($obj:expr),*$(,)?) => {
-        RenderComponent::column(vec![
+    ($($obj:expr $(=> $constraint:expr)?),*$(,)?) => {
+        $crate::core::LayoutNode::column_with_constraints(vec![
         $(
-           $obj.into(),
+           $crate::layout_child!($obj $(, $constraint)?),
         )*
 
         ])
     };
 }
 
+/// Creates a row of widgets, as a `LayoutNode` like `column_widget!`. Each widget
+/// may be followed by `=> constraint` to control its size, e.g. `row_widget!(Sidebar {} => Constraint::Length(30), Content {})`.
+/// Widgets without a constraint evenly share the remaining space. Keys are given,
+/// and checked, as in `column_widget!`.
 #[macro_export]
 macro_rules! row_widget {
-    ($($obj:expr),*$(,)?) => {
-        RenderComponent::row(vec![
+    ($($obj:expr $(=> $constraint:expr)?),*$(,)?) => {
+        $crate::core::LayoutNode::row_with_constraints(vec![
         $(
-           $obj.into(),
+           $crate::layout_child!($obj $(, $constraint)?),
         )*
 
         ])
     };
 }
 
+#[doc(hidden)]
+#[macro_export]
+macro_rules! layout_child {
+    ($obj:expr) => {
+        ($crate::core::DEFAULT_CONSTRAINT, $obj.into())
+    };
+    ($obj:expr, $options:expr) => {
+        $crate::core::ChildOptions::from($options).apply($obj.into())
+    };
+}
+
 pub use column_widget;
 pub use row_widget;
@@ -3,12 +3,13 @@ use std::any::Any;
 use ratatui::{buffer::Buffer, layout::Rect};
 
 use crate::{
-    core::{InputEvent, RenderComponent},
-    windows::{alerts::AlertManager, menu::Menu},
+    core::{InputEvent, RenderComponent, RenderId},
+    windows::{alerts::AlertManager, focus::FocusHandle, menu::Menu},
 };
 
 pub trait AsAny {
     fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
 }
 
 pub trait Render: AsAny {
@@ -23,11 +24,26 @@ pub trait Render: AsAny {
     fn get_menu(&self) -> Option<Menu> {
         None
     }
+
+    /// Handles an event targeted at this component. It is called before drawing,
+    /// which should only reflect the state left by the handled events. Events
+    /// that are ignored bubble up to the component's menu, the layouts containing
+    /// it, the page menu and finally the window shortcuts.
+    #[allow(unused_variables)]
+    fn on_event(&mut self, event: &InputEvent, ctx: &mut EventContext) -> EventResult {
+        EventResult::Ignored
+    }
 }
 
 pub trait FocusableRender: Render {
     fn render(&mut self, render_props: &RenderProps, buff: &mut Buffer, area: Rect);
 
+    /// See [`Render::on_event`]
+    #[allow(unused_variables)]
+    fn on_event(&mut self, event: &InputEvent, ctx: &mut EventContext) -> EventResult {
+        EventResult::Ignored
+    }
+
     #[allow(unused_variables)]
     fn render_footer(&mut self, render_props: &RenderProps, buff: &mut Buffer, area: Rect) {}
 
@@ -51,12 +67,20 @@ impl<T: FocusableRender> Render for T {
     fn get_menu(&self) -> Option<Menu> {
         FocusableRender::get_menu(self)
     }
+
+    fn on_event(&mut self, event: &InputEvent, ctx: &mut EventContext) -> EventResult {
+        FocusableRender::on_event(self, event, ctx)
+    }
 }
 
 impl<T: Render + 'static> AsAny for T {
     fn as_any(&self) -> &dyn Any {
         self
     }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
 }
 
 impl<T: Render + 'static> From<T> for RenderComponent {
@@ -66,17 +90,51 @@ impl<T: Render + 'static> From<T> for RenderComponent {
 }
 
 pub trait RenderFactory {
+    /// Builds the components, which are kept until the factory is invalidated
+    /// through its `FactoryHandle` or asks to be rebuilt
     fn render(&mut self) -> RenderComponent;
+
+    /// Checked before every frame, to rebuild the components when the state
+    /// they were built from changed
+    fn should_rebuild(&mut self) -> bool {
+        false
+    }
 }
 
+/// What a component can read while drawing. Events are not part of it: they
+/// are handled by `on_event` before drawing, so redraws have no side effects.
 #[derive(Debug)]
 pub struct RenderProps<'a> {
     pub is_focused: bool,
-    pub event: Option<InputEvent>,
     pub event_buffer: Vec<InputEvent>,
     pub alerts: &'a AlertManager,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventResult {
+    /// The event was handled and stops propagating
+    Consumed,
+    /// The event keeps bubbling up
+    Ignored,
+}
+
+impl EventResult {
+    pub fn is_consumed(&self) -> bool {
+        *self == EventResult::Consumed
+    }
+}
+
+/// What a component can reach while handling an event
+pub struct EventContext<'a> {
+    /// The component (or layout) handling the event
+    pub id: RenderId,
+    pub alerts: &'a mut AlertManager,
+    /// Reads and moves the focus of the page
+    pub focus: FocusHandle,
+}
+
+pub type LayoutEventHandler = Box<dyn FnMut(&InputEvent, &mut EventContext) -> EventResult>;
+
 pub trait RenderTimed {
     fn is_visible(&self) -> bool;
 }
@@ -5,18 +5,17 @@ use ratatui::{
     widgets::{Block, Borders, List, Paragraph, Widget, Wrap},
 };
 use std::{error::Error, time::Duration};
-use tuiwindow::core::RenderComponent;
 use tuiwindow::{
-    core::InputEvent,
-    hooks::AsyncResource,
-    render::{FocusableRender, Render, RenderProps},
+    core::{InputEvent, KeyCode},
+    hooks::{AsyncResource, ResourceState},
+    render::{EventContext, EventResult, FocusableRender, Render, RenderProps},
     tui::TuiCrossterm,
     windows::{
         alerts::Alert,
         menu::{Menu, MenuEvent},
         page::Page,
         page_collection::PageCollection,
-        window::{DefaultEventMapper, Window},
+        window::Window,
     },
 };
 #[macro_use]
@@ -36,8 +35,20 @@ fn fib_cpu_intensive(n: u64) -> u64 {
 }
 
 impl FocusableRender for SlowWidget {
+    fn on_event(&mut self, event: &InputEvent, _ctx: &mut EventContext) -> EventResult {
+        if event.as_char() == Some('r') {
+            self.task.refresh();
+            EventResult::Consumed
+        } else {
+            EventResult::Ignored
+        }
+    }
+
     fn render(&mut self, render_props: &RenderProps, buff: &mut Buffer, area: Rect) {
-        let maybe_result = self.task.with_thread_spawning(|| fib_cpu_intensive(40));
+        let text = match self.task.load(|_| Ok(fib_cpu_intensive(40))) {
+            ResourceState::Ready(result) => format!("Very slow: {result} (r to refresh)"),
+            state => format!("Very slow: loading... (previous: {:?})", state.value()),
+        };
 
         let block = Block::new()
             .borders(Borders::all())
@@ -46,11 +57,7 @@ impl FocusableRender for SlowWidget {
             } else {
                 Style::new()
             });
-        Widget::render(
-            Paragraph::new(format!("Very slow: {:?}", maybe_result)).block(block),
-            area,
-            buff,
-        );
+        Widget::render(Paragraph::new(text).block(block), area, buff);
     }
 }
 
@@ -94,10 +101,20 @@ struct TestWidget {
 }
 
 impl FocusableRender for TestWidget {
-    fn render(&mut self, render_props: &RenderProps, buff: &mut Buffer, area: Rect) {
-        if let Some(InputEvent::Key(c)) = render_props.event {
-            self.text_content.push(c)
+    fn on_event(&mut self, event: &InputEvent, _ctx: &mut EventContext) -> EventResult {
+        if let InputEvent::Key(key) = event {
+            if let Some(c) = key.as_char() {
+                self.text_content.push(c);
+                return EventResult::Consumed;
+            } else if key.code == KeyCode::Backspace {
+                self.text_content.pop();
+                return EventResult::Consumed;
+            }
         }
+        EventResult::Ignored
+    }
+
+    fn render(&mut self, render_props: &RenderProps, buff: &mut Buffer, area: Rect) {
         Paragraph::new(format!(
             "Hello world! Focused? {}: {}",
             render_props.is_focused, self.text_content
@@ -129,7 +146,6 @@ impl Render for StaticWidget {
 
 fn main() -> Result<(), Box<dyn Error>> {
     let mut tui = TuiCrossterm::new()?;
-    let terminal = tui.setup()?;
 
     // define the collection of pages:
 
@@ -153,23 +169,9 @@ fn main() -> Result<(), Box<dyn Error>> {
         .with_style(Style::default().bg(Color::White).fg(Color::Black)),
     ]);
 
-    let mut window = Window::new(&app, |ev| match ev {
-        tuiwindow::core::InputEvent::Key(c) => *c == 'q',
-        _ => false,
-    });
+    let mut window = Window::new(&app, |ev| ev.as_char() == Some('q'));
 
-    while !window.is_finished() {
-        terminal.draw(|f| {
-            let area = f.size();
-            let buff = f.buffer_mut();
-
-            let mut second_buff = buff.clone();
-            // draw
-            window.render::<DefaultEventMapper>(&mut app, &mut second_buff, area);
-
-            buff.merge(&second_buff);
-        })?;
-    }
+    tui.run(&mut app, &mut window)?;
 
     Ok(())
 }
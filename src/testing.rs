@@ -0,0 +1,382 @@
+//! Helpers to test applications without a terminal: a [`TestDriver`] feeds
+//! events to a `Window` and renders every frame into a Ratatui `TestBackend`,
+//! and [`Snapshot`] compares rendered buffers with snapshots stored on disk.
+
+use std::{env, fs, io::ErrorKind, path::PathBuf};
+
+use ratatui::{
+    backend::TestBackend,
+    buffer::{Buffer, Cell},
+    layout::Rect,
+    Terminal,
+};
+
+use crate::{
+    core::{ComponentBuffer, InputEvent, RenderFlow, RenderId, VRenderProps},
+    events::{EventSource, ScriptedEventSource},
+    windows::{
+        alerts::{Alert, AlertManager},
+        page::Page,
+        page_collection::PageCollection,
+        window::Window,
+    },
+};
+
+pub struct TestDriver {
+    app: PageCollection,
+    window: Window,
+    terminal: Terminal<TestBackend>,
+}
+
+impl TestDriver {
+    /// Creates a driver for a window that never finishes on its own
+    pub fn new(app: PageCollection, width: u16, height: u16) -> Self {
+        let window = Window::new(&app, |_| false);
+        Self::with_window(app, window, width, height)
+    }
+
+    pub fn with_window(app: PageCollection, window: Window, width: u16, height: u16) -> Self {
+        let terminal = Terminal::new(TestBackend::new(width, height))
+            .expect("the test backend can't fail to initialize");
+        let mut driver = Self {
+            app,
+            window,
+            terminal,
+        };
+        driver.render();
+        driver
+    }
+
+    /// Draws a frame
+    pub fn render(&mut self) -> &mut Self {
+        let Self {
+            app,
+            window,
+            terminal,
+        } = self;
+        terminal
+            .draw(|f| {
+                let area = f.size();
+                window.render(app, f.buffer_mut(), area)
+            })
+            .expect("the test backend can't fail to draw");
+        self
+    }
+
+    /// Handles an event and draws the resulting frame
+    pub fn send(&mut self, event: InputEvent) -> &mut Self {
+        self.window.handle_event(&mut self.app, event);
+        self.render()
+    }
+
+    /// Sends every event, drawing a frame after each one
+    pub fn send_all<I: IntoIterator<Item = InputEvent>>(&mut self, events: I) -> &mut Self {
+        let mut source = ScriptedEventSource::new(events);
+        while let Ok(Some(event)) = source.next_event(Default::default()) {
+            self.send(event);
+        }
+        self
+    }
+
+    /// Delivers the ticks of the timers that fired since the last call, drawing
+    /// a frame if there were any. Returns whether there were any.
+    pub fn handle_timer_ticks(&mut self) -> bool {
+        let handled = self.window.handle_timer_ticks(&mut self.app);
+        if handled {
+            self.render();
+        }
+        handled
+    }
+
+    /// Types every character of `text` as a key press
+    pub fn type_text(&mut self, text: &str) -> &mut Self {
+        self.send_all(text.chars().map(|c| InputEvent::Key(c.into())))
+    }
+
+    /// Resizes the terminal, sending the matching event
+    pub fn resize(&mut self, width: u16, height: u16) -> &mut Self {
+        self.terminal.backend_mut().resize(width, height);
+        self.send(InputEvent::Resize(width, height))
+    }
+
+    /// The last frame drawn
+    pub fn buffer(&self) -> &Buffer {
+        self.terminal.backend().buffer()
+    }
+
+    /// The text of every line of the last frame
+    pub fn lines(&self) -> Vec<String> {
+        let buffer = self.buffer();
+        (0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer.get(x, y).symbol())
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Whether the last frame shows `text` within a single line
+    pub fn contains_text(&self, text: &str) -> bool {
+        self.lines().iter().any(|line| line.contains(text))
+    }
+
+    /// The focused component, `None` if the focus is on the window
+    pub fn focused(&self) -> Option<RenderId> {
+        self.window.focused()
+    }
+
+    /// See `Window::focus_key`
+    pub fn focus_key(&mut self, key: &str) -> bool {
+        self.window.focus_key(&mut self.app, key)
+    }
+
+    pub fn current_page(&self) -> &Page {
+        self.app.get_current_page()
+    }
+
+    /// The alert currently shown, if any
+    pub fn visible_alert(&mut self) -> Option<&Alert> {
+        self.window.alerts_mut().first_visible().map(|a| &*a)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.window.is_finished()
+    }
+
+    /// Compares the last frame with a stored snapshot
+    pub fn assert_snapshot(&self, snapshot: Snapshot) {
+        snapshot.assert(self.buffer())
+    }
+
+    pub fn app(&self) -> &PageCollection {
+        &self.app
+    }
+
+    pub fn app_mut(&mut self) -> &mut PageCollection {
+        &mut self.app
+    }
+
+    pub fn window(&self) -> &Window {
+        &self.window
+    }
+
+    pub fn window_mut(&mut self) -> &mut Window {
+        &mut self.window
+    }
+}
+
+/// Renders anything that can be laid out (a `Page` or a `RenderComponent`) into
+/// a new buffer of the given size, with no focused element
+pub fn render_to_buffer<R: RenderFlow>(component: &mut R, width: u16, height: u16) -> Buffer {
+    let area = Rect::new(0, 0, width, height);
+    let mut buffer = Buffer::empty(area);
+    component.render(
+        &mut VRenderProps {
+            focused_element: None,
+            alerts: &mut AlertManager::default(),
+        },
+        &mut ComponentBuffer::default(),
+        &mut buffer,
+        area,
+    );
+    buffer
+}
+
+/// Set this env var to write the rendered buffers over the stored snapshots
+pub const UPDATE_SNAPSHOTS_VAR: &str = "TUIWINDOW_UPDATE_SNAPSHOTS";
+
+/// Compares a buffer with a text snapshot stored under `tests/snapshots/` in the
+/// crate being tested. A missing snapshot is an error: run the tests with
+/// `TUIWINDOW_UPDATE_SNAPSHOTS=1` to write it, then commit it.
+///
+/// ```no_run
+/// # use tuiwindow::testing::Snapshot;
+/// # let buffer = ratatui::buffer::Buffer::empty(ratatui::layout::Rect::new(0, 0, 1, 1));
+/// Snapshot::new("my_page").with_styles().assert(&buffer);
+/// ```
+pub struct Snapshot {
+    name: String,
+    dir: PathBuf,
+    compare_styles: bool,
+}
+
+impl Snapshot {
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        let root = env::var_os("CARGO_MANIFEST_DIR").unwrap_or_default();
+        Self {
+            name: name.into(),
+            dir: PathBuf::from(root).join("tests").join("snapshots"),
+            compare_styles: false,
+        }
+    }
+
+    /// Stores the snapshots somewhere else than `tests/snapshots/`
+    pub fn in_dir<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.dir = dir.into();
+        self
+    }
+
+    /// Records the style of the cells as well as their symbols
+    pub fn with_styles(mut self) -> Self {
+        self.compare_styles = true;
+        self
+    }
+
+    pub fn path(&self) -> PathBuf {
+        self.dir.join(format!("{}.snap", self.name))
+    }
+
+    /// Panics with a line diff if the buffer doesn't match the snapshot
+    pub fn assert(&self, buffer: &Buffer) {
+        if let Err(diff) = self.check(buffer) {
+            panic!("{diff}")
+        }
+    }
+
+    /// Like [`Snapshot::assert`], returning the diff instead of panicking
+    pub fn check(&self, buffer: &Buffer) -> Result<(), String> {
+        if env::var_os(UPDATE_SNAPSHOTS_VAR).is_some() {
+            self.update(buffer)
+        } else {
+            self.compare(buffer)
+        }
+    }
+
+    /// Compares the buffer with the stored snapshot, whatever the environment
+    fn compare(&self, buffer: &Buffer) -> Result<(), String> {
+        let actual = buffer_to_text(buffer, self.compare_styles);
+        let path = self.path();
+        match fs::read_to_string(&path) {
+            Ok(expected) if expected == actual => Ok(()),
+            Ok(expected) => Err(format!(
+                "snapshot {} doesn't match (set {UPDATE_SNAPSHOTS_VAR}=1 to update it):\n{}",
+                path.display(),
+                diff_lines(&expected, &actual)
+            )),
+            Err(e) if e.kind() == ErrorKind::NotFound => Err(format!(
+                "snapshot {} doesn't exist (set {UPDATE_SNAPSHOTS_VAR}=1 to write it)",
+                path.display()
+            )),
+            Err(e) => Err(format!("can't read snapshot {}: {e}", path.display())),
+        }
+    }
+
+    /// Writes the buffer over the stored snapshot
+    pub fn update(&self, buffer: &Buffer) -> Result<(), String> {
+        let path = self.path();
+        fs::create_dir_all(&self.dir)
+            .and_then(|_| fs::write(&path, buffer_to_text(buffer, self.compare_styles)))
+            .map_err(|e| format!("can't write snapshot {}: {e}", path.display()))
+    }
+}
+
+/// The text of a buffer, one line per row. With `with_styles`, every run of
+/// cells sharing a non default style is listed after the text.
+pub fn buffer_to_text(buffer: &Buffer, with_styles: bool) -> String {
+    let area = buffer.area;
+    let mut text = String::new();
+    for y in area.top()..area.bottom() {
+        for x in area.left()..area.right() {
+            text.push_str(buffer.get(x, y).symbol());
+        }
+        text.push('\n');
+    }
+
+    if with_styles {
+        let default_style = Cell::default().style();
+        text.push_str("---\n");
+        for y in area.top()..area.bottom() {
+            let mut x = area.left();
+            while x < area.right() {
+                let style = buffer.get(x, y).style();
+                let start = x;
+                while x < area.right() && buffer.get(x, y).style() == style {
+                    x += 1;
+                }
+                if style != default_style {
+                    text.push_str(&format!("{y}:{start}..{x} {style:?}\n"));
+                }
+            }
+        }
+    }
+    text
+}
+
+fn diff_lines(expected: &str, actual: &str) -> String {
+    let expected: Vec<_> = expected.lines().collect();
+    let actual: Vec<_> = actual.lines().collect();
+    let mut diff = String::new();
+    for i in 0..expected.len().max(actual.len()) {
+        match (expected.get(i), actual.get(i)) {
+            (Some(e), Some(a)) if e == a => diff.push_str(&format!("  |{e}|\n")),
+            (e, a) => {
+                if let Some(e) = e {
+                    diff.push_str(&format!("- |{e}|\n"));
+                }
+                if let Some(a) = a {
+                    diff.push_str(&format!("+ |{a}|\n"));
+                }
+            }
+        }
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::{
+        buffer::{Buffer, Cell},
+        layout::Rect,
+        style::{Color, Style},
+    };
+
+    use super::{buffer_to_text, diff_lines, Snapshot};
+
+    fn buffer() -> Buffer {
+        let mut buffer = Buffer::empty(Rect::new(0, 0, 4, 2));
+        buffer.set_string(0, 0, "ab", Style::default().fg(Color::Red));
+        buffer.set_string(1, 1, "cd", Style::default());
+        buffer
+    }
+
+    #[test]
+    fn test_buffer_to_text() {
+        assert_eq!(buffer_to_text(&buffer(), false), "ab  \n cd \n");
+        assert_eq!(
+            buffer_to_text(&buffer(), true),
+            format!(
+                "ab  \n cd \n---\n0:0..2 {:?}\n",
+                Cell::default().set_fg(Color::Red).style()
+            )
+        );
+    }
+
+    #[test]
+    fn test_diff_lines() {
+        assert_eq!(
+            diff_lines("a\nb\n", "a\nc\nd\n"),
+            "  |a|\n- |b|\n+ |c|\n+ |d|\n"
+        );
+    }
+
+    #[test]
+    fn test_snapshot_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("tuiwindow-snapshots-{}", std::process::id()));
+        let snapshot = Snapshot::new("roundtrip").in_dir(&dir).with_styles();
+
+        let missing = snapshot.compare(&buffer()).unwrap_err();
+        assert!(missing.contains("doesn't exist"), "{missing}");
+        assert!(!snapshot.path().exists());
+
+        snapshot.update(&buffer()).unwrap();
+        snapshot.compare(&buffer()).unwrap();
+
+        let mut changed = buffer();
+        changed.set_string(0, 0, "ab", Style::default().fg(Color::Blue));
+        let diff = snapshot.compare(&changed).unwrap_err();
+        assert!(diff.contains("- |0:0..2"), "{diff}");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,12 +1,17 @@
-use std::{error::Error, io::Stdout};
+use std::{error::Error, io::Stdout, sync::Once};
 
 use crossterm::{
-    event::{DisableMouseCapture, EnableMouseCapture},
+    event::{DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{backend::CrosstermBackend, Terminal};
 
+use crate::windows::{
+    page_collection::PageCollection,
+    window::{ExitReason, Window},
+};
+
 pub struct TuiCrossterm {
     terminal: Terminal<CrosstermBackend<Stdout>>,
 }
@@ -18,32 +23,63 @@ impl TuiCrossterm {
 
         Ok(Self { terminal })
     }
+    /// Restores the terminal before reporting a panic. The hook is installed
+    /// once, however many times the terminal is set up.
     fn initialize_panic_handler() {
-        let original_hook = std::panic::take_hook();
-        std::panic::set_hook(Box::new(move |panic_info| {
-            crossterm::execute!(std::io::stderr(), crossterm::terminal::LeaveAlternateScreen)
+        static INSTALL_HOOK: Once = Once::new();
+        INSTALL_HOOK.call_once(|| {
+            let original_hook = std::panic::take_hook();
+            std::panic::set_hook(Box::new(move |panic_info| {
+                crossterm::execute!(
+                    std::io::stderr(),
+                    LeaveAlternateScreen,
+                    DisableMouseCapture,
+                    DisableBracketedPaste
+                )
                 .unwrap();
-            crossterm::terminal::disable_raw_mode().unwrap();
-            original_hook(panic_info);
-        }));
+                crossterm::terminal::disable_raw_mode().unwrap();
+                original_hook(panic_info);
+            }));
+        });
     }
 
     pub fn setup(&mut self) -> Result<&mut Terminal<CrosstermBackend<Stdout>>, Box<dyn Error>> {
         TuiCrossterm::initialize_panic_handler();
         let mut stdout = std::io::stdout();
 
-        execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+        execute!(
+            stdout,
+            EnterAlternateScreen,
+            EnableMouseCapture,
+            EnableBracketedPaste
+        )?;
         enable_raw_mode()?;
         self.terminal.clear()?;
         Ok(&mut self.terminal)
     }
 
+    /// Sets the terminal up, runs the window until it finishes and restores the
+    /// terminal, also when running fails
+    pub fn run(
+        &mut self,
+        app: &mut PageCollection,
+        window: &mut Window,
+    ) -> Result<ExitReason, Box<dyn Error>> {
+        let result = window.run(app, self.setup()?);
+        // an error while running matters more than one while tearing down
+        let torn_down = self.tear_down();
+        let reason = result?;
+        torn_down?;
+        Ok(reason)
+    }
+
     fn tear_down(&mut self) -> Result<(), Box<dyn Error>> {
         disable_raw_mode()?;
         execute!(
             self.terminal.backend_mut(),
             LeaveAlternateScreen,
-            DisableMouseCapture
+            DisableMouseCapture,
+            DisableBracketedPaste
         )?;
         self.terminal.show_cursor()?;
 
@@ -1,5 +1,19 @@
 use std::{collections::HashMap, hash::Hash};
 
+/// Polls `condition` until it holds, failing the test after `timeout`
+#[cfg(test)]
+#[track_caller]
+pub(crate) fn wait_until(mut condition: impl FnMut() -> bool, timeout: std::time::Duration) {
+    let deadline = std::time::Instant::now() + timeout;
+    while !condition() {
+        assert!(
+            std::time::Instant::now() < deadline,
+            "timed out after {timeout:?}"
+        );
+        std::thread::sleep(std::time::Duration::from_millis(1));
+    }
+}
+
 pub struct CyclicList<T> {
     elements: Vec<T>,
     currently_selected: usize,
@@ -35,6 +49,32 @@ impl<T> CyclicList<T> {
     pub fn reset(&mut self) {
         self.currently_selected = 0;
     }
+
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    /// Selects the element at `index`, returning whether there is one
+    pub fn select_index(&mut self, index: usize) -> bool {
+        if index < self.elements.len() {
+            self.currently_selected = index;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl<T: PartialEq> CyclicList<T> {
+    /// Selects the given element, returning whether it is part of the list
+    pub fn select(&mut self, element: &T) -> bool {
+        if let Some(i) = self.elements.iter().position(|e| e == element) {
+            self.currently_selected = i;
+            true
+        } else {
+            false
+        }
+    }
 }
 
 pub struct SelectableHashMap<K: Eq + PartialEq + Hash, V> {
@@ -34,6 +34,14 @@ impl Alert {
 }
 
 impl Alert {
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
     pub fn render(&mut self, area: Rect, buf: &mut Buffer)
     where
         Self: Sized,
@@ -73,7 +81,20 @@ impl Alert {
     }
 }
 
+impl Alert {
+    /// When the countdown shown by the alert changes next, or when it expires.
+    /// `None` until it is first drawn.
+    fn next_change(&self) -> Option<Instant> {
+        self.rendered_time.map(|time| {
+            let next_second = Duration::from_secs(time.elapsed().as_secs() + 1);
+            time + next_second.min(self.duration)
+        })
+    }
+}
+
 impl RenderTimed for Alert {
+    // `Option::is_none_or` would need Rust 1.82
+    #[allow(clippy::unnecessary_map_or)]
     fn is_visible(&self) -> bool {
         self.rendered_time
             .map_or(true, |t| t.elapsed() < self.duration)
@@ -90,6 +111,15 @@ impl AlertManager {
         self.alerts.iter_mut().find(|a| a.is_visible())
     }
 
+    /// When the alert being shown needs to be drawn again, to update its
+    /// countdown or to go away
+    pub(crate) fn next_redraw(&self) -> Option<Instant> {
+        self.alerts
+            .iter()
+            .find(|a| a.is_visible())
+            .and_then(|a| a.next_change())
+    }
+
     pub fn schedule(&mut self, alert: Alert) {
         self.alerts.push(alert)
     }
@@ -99,3 +129,34 @@ impl AlertManager {
             .push(Alert::new("Alert", msg, Duration::from_secs(1)))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{Alert, AlertManager};
+
+    #[test]
+    fn test_next_redraw() {
+        let mut alerts = AlertManager::default();
+        assert_eq!(alerts.next_redraw(), None);
+
+        alerts.schedule(Alert::new("Title", "Message", Duration::from_millis(2500)));
+        // drawn right away by the event that scheduled it
+        assert_eq!(alerts.next_redraw(), None);
+
+        let shown = Instant::now() - Duration::from_millis(1500);
+        alerts.alerts[0].rendered_time = Some(shown);
+        assert_eq!(alerts.next_redraw(), Some(shown + Duration::from_secs(2)));
+
+        let shown = Instant::now() - Duration::from_millis(2200);
+        alerts.alerts[0].rendered_time = Some(shown);
+        assert_eq!(
+            alerts.next_redraw(),
+            Some(shown + Duration::from_millis(2500))
+        );
+
+        alerts.alerts[0].rendered_time = Some(Instant::now() - Duration::from_secs(3));
+        assert_eq!(alerts.next_redraw(), None);
+    }
+}
@@ -0,0 +1,103 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::core::RenderId;
+
+thread_local! {
+    static CURRENT_FOCUS: RefCell<Option<FocusHandle>> = const { RefCell::new(None) };
+}
+
+/// Where the focus of the current page should move
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FocusRequest {
+    Id(RenderId),
+    Key(String),
+    /// The first focusable component, in render order
+    First,
+    /// The last focusable component, in render order
+    Last,
+    /// The window itself, as with Esc
+    Window,
+}
+
+#[derive(Default, Debug)]
+struct FocusState {
+    focused: Option<RenderId>,
+    request: Option<FocusRequest>,
+}
+
+/// The focus as seen from event and menu handlers (see `EventContext::focus`
+/// and `MenuEvent::focus`). The window moves the focus once the event is
+/// handled; if several moves were asked for, the last one wins.
+///
+/// Outside of `Window::handle_event` the handle is detached: nothing is
+/// focused and the requests are dropped.
+#[derive(Clone, Default, Debug)]
+pub struct FocusHandle {
+    state: Rc<RefCell<FocusState>>,
+}
+
+impl FocusHandle {
+    /// The handle of the event being handled on this thread, or a detached one
+    pub fn current() -> FocusHandle {
+        CURRENT_FOCUS.with(|current| current.borrow().clone().unwrap_or_default())
+    }
+
+    /// The focused component when the event was delivered, `None` if the focus
+    /// is on the window
+    pub fn focused(&self) -> Option<RenderId> {
+        self.state.borrow().focused
+    }
+
+    pub fn focus(&self, id: RenderId) {
+        self.request(FocusRequest::Id(id))
+    }
+
+    pub fn focus_key<S: Into<String>>(&self, key: S) {
+        self.request(FocusRequest::Key(key.into()))
+    }
+
+    pub fn focus_first(&self) {
+        self.request(FocusRequest::First)
+    }
+
+    pub fn focus_last(&self) {
+        self.request(FocusRequest::Last)
+    }
+
+    pub fn focus_window(&self) {
+        self.request(FocusRequest::Window)
+    }
+
+    pub fn request(&self, request: FocusRequest) {
+        self.state.borrow_mut().request = Some(request);
+    }
+
+    pub(crate) fn take_request(&self) -> Option<FocusRequest> {
+        self.state.borrow_mut().request.take()
+    }
+
+    /// Makes a handle seeing `focused` the `current` one until the guard is
+    /// dropped
+    pub(crate) fn enter(focused: Option<RenderId>) -> FocusGuard {
+        let handle = FocusHandle {
+            state: Rc::new(RefCell::new(FocusState {
+                focused,
+                request: None,
+            })),
+        };
+        let previous = CURRENT_FOCUS.with(|current| current.replace(Some(handle.clone())));
+        FocusGuard { handle, previous }
+    }
+}
+
+pub(crate) struct FocusGuard {
+    pub(crate) handle: FocusHandle,
+    previous: Option<FocusHandle>,
+}
+
+impl Drop for FocusGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        CURRENT_FOCUS.with(|current| *current.borrow_mut() = previous);
+    }
+}
@@ -1,11 +1,13 @@
 use std::rc::Rc;
 
-use crate::core::InputEvent;
+use crate::{core::InputEvent, render::EventResult};
 
-use super::alerts::AlertManager;
+use super::{alerts::AlertManager, focus::FocusHandle};
 
 pub struct MenuEvent<'a> {
     pub alerts: &'a mut AlertManager,
+    /// Reads and moves the focus of the page
+    pub focus: FocusHandle,
 }
 
 pub type MenuItemEventHandler = Box<dyn Fn(MenuEvent)>;
@@ -60,15 +62,25 @@ impl Menu {
         self.menu_content.append(&mut other.menu_content);
     }
 
-    pub fn handle_event(&mut self, alert_manager: &mut AlertManager, event: &InputEvent) {
-        if let InputEvent::Key(key) = event {
+    /// Runs the entries whose shortcut matches the typed key, consuming the event
+    /// if there was any
+    pub fn handle_event(
+        &mut self,
+        alert_manager: &mut AlertManager,
+        event: &InputEvent,
+    ) -> EventResult {
+        let mut result = EventResult::Ignored;
+        if let Some(key) = event.as_char() {
             for menu_item in &self.menu_content {
-                if menu_item.shortcut == *key {
+                if menu_item.shortcut == key {
                     (menu_item.handler)(MenuEvent {
                         alerts: alert_manager,
-                    })
+                        focus: FocusHandle::current(),
+                    });
+                    result = EventResult::Consumed;
                 }
             }
         }
+        result
     }
 }
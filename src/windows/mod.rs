@@ -1,4 +1,5 @@
 pub mod alerts;
+pub mod focus;
 pub mod menu;
 pub mod page;
 pub mod page_collection;
@@ -1,24 +1,46 @@
-use ratatui::{
-    layout::{Position, Rect},
-    style::Style,
-};
+use std::{any::Any, error::Error, fmt};
+
+use ratatui::{layout::Position, style::Style};
 
 use crate::{
-    area_calculation::{unroll, UnrolledComponents},
     core::RenderId,
-    core::{RenderComponent, RenderFlow, RenderNode},
+    core::{InputEvent, RenderComponent, RenderFlow, RenderNode},
+    render::{EventResult, Render},
+};
+
+use super::{
+    alerts::AlertManager,
+    menu::{Menu, MenuEvent},
 };
 
-use super::menu::{Menu, MenuEvent};
+/// Two components or layouts of a page were given the same key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateKeyError {
+    pub page: String,
+    pub key: String,
+}
+
+impl fmt::Display for DuplicateKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "the key \"{}\" is used more than once in page \"{}\"",
+            self.key, self.page
+        )
+    }
+}
+
+impl Error for DuplicateKeyError {}
 
 pub struct Page {
     id: RenderId,
     pub(crate) title: String,
     pub(crate) shortcut: char,
     root: RenderComponent,
-    unrolled: UnrolledComponents,
-    menu: Menu,
+    pub(crate) menu: Menu,
     pub(crate) style: Style,
+    is_tree_changed: bool,
+    key_error: Option<DuplicateKeyError>,
 }
 
 impl Page {
@@ -26,22 +48,71 @@ impl Page {
         &self.id
     }
 
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn shortcut(&self) -> char {
+        self.shortcut
+    }
+
+    /// Creates a page. A key used more than once is only caught by a debug
+    /// assertion, see `Page::try_new` to handle it.
     pub fn new<T: Into<RenderComponent>, S: Into<String>>(
         title: S,
         shortcut: char,
         root: T,
     ) -> Self {
-        let root: RenderComponent = root.into();
-        let layout_factories = unroll(&root);
+        let page = Self::new_unchecked(title, shortcut, root);
+        if let Err(err) = page.check_keys() {
+            debug_assert!(false, "{err}, use Page::try_new to handle it");
+        }
+        page
+    }
+
+    /// Like `Page::new`, without checking the keys at all
+    pub(crate) fn new_unchecked<T: Into<RenderComponent>, S: Into<String>>(
+        title: S,
+        shortcut: char,
+        root: T,
+    ) -> Self {
         Self {
             id: RenderId::new(),
             shortcut,
             title: title.into(),
-            root,
+            root: root.into(),
             menu: Menu::default(),
-            unrolled: layout_factories,
             style: Style::default(),
+            is_tree_changed: false,
+            key_error: None,
+        }
+    }
+
+    /// Like `Page::new`, failing if a key is used more than once
+    pub fn try_new<T: Into<RenderComponent>, S: Into<String>>(
+        title: S,
+        shortcut: char,
+        root: T,
+    ) -> Result<Self, DuplicateKeyError> {
+        let page = Self::new_unchecked(title, shortcut, root);
+        page.check_keys().map(|_| page)
+    }
+
+    /// Replaces the components of the page. Those with a key found in the
+    /// current ones keep their state, id and focus (see `RenderComponent::reconcile`).
+    /// Fails, keeping the current components, if the new ones use a key twice.
+    pub fn set_root<T: Into<RenderComponent>>(&mut self, root: T) -> Result<(), DuplicateKeyError> {
+        let mut root = root.into();
+        if let Some(key) = root.duplicate_key() {
+            return Err(self.duplicate_key_error(key));
         }
+        root.reconcile(std::mem::replace(
+            &mut self.root,
+            RenderComponent::row(vec![]),
+        ));
+        self.root = root;
+        self.is_tree_changed = true;
+        Ok(())
     }
 
     pub fn with_style(mut self, style: Style) -> Self {
@@ -66,19 +137,137 @@ impl Page {
         self.root.visit(f);
     }
 
-    pub fn components_at_position(&self, pos: &Position, area: &Rect) -> Vec<&RenderId> {
-        self.unrolled
-            .0
-            .iter()
-            .filter_map(|(id, area_calc)| {
-                let sub_area = area_calc(*area);
-                if sub_area.contains(*pos) {
-                    Some(id)
-                } else {
-                    None
-                }
-            })
-            .collect()
+    /// Like `visit`, with mutable access to the nodes, e.g. to update a widget
+    /// from outside of the render loop. Stale factories are rebuilt first, so
+    /// that the changes aren't lost with their old components; a rebuild using
+    /// a key twice is dropped and left in `take_key_error`. The nodes may
+    /// change, e.g. whether they can be focused, so the window looks at the
+    /// whole page again after.
+    pub fn visit_mut(&mut self, f: &mut dyn FnMut(&mut RenderNode) -> bool) -> bool {
+        self.refresh_root();
+        self.is_tree_changed = true;
+        self.root.visit_mut(f)
+    }
+
+    /// See `RenderComponent::id_of`
+    pub fn id_of(&self, key: &str) -> Option<RenderId> {
+        self.root.id_of(key)
+    }
+
+    /// Checks that no key is used twice in the page
+    pub fn check_keys(&self) -> Result<(), DuplicateKeyError> {
+        match self.root.duplicate_key() {
+            Some(key) => Err(self.duplicate_key_error(key)),
+            None => Ok(()),
+        }
+    }
+
+    /// The error of the last factory rebuild that was dropped because it used
+    /// a key found elsewhere in the page. The factory kept its previous
+    /// components, until it is invalidated again.
+    pub fn take_key_error(&mut self) -> Option<DuplicateKeyError> {
+        self.key_error.take()
+    }
+
+    fn duplicate_key_error(&self, key: String) -> DuplicateKeyError {
+        DuplicateKeyError {
+            page: self.title.clone(),
+            key,
+        }
+    }
+
+    /// The widget of the component with the given id, if it is a `T`. Stale
+    /// factories are rebuilt first, as in `visit_mut`.
+    pub fn find_by_id<T: Render + Any>(&mut self, id: &RenderId) -> Option<&mut T> {
+        self.is_tree_changed |= self.refresh_root();
+        self.root.find_by_id(id)
+    }
+
+    /// The widget of the component with the given key, if it is a `T`. Stale
+    /// factories are rebuilt first, as in `visit_mut`.
+    pub fn find_by_key<T: Render + Any>(&mut self, key: &str) -> Option<&mut T> {
+        self.is_tree_changed |= self.refresh_root();
+        self.root.find_by_key(key)
+    }
+
+    /// Every widget that is a `T`, in render order. Stale factories are rebuilt
+    /// first, as in `visit_mut`.
+    pub fn find_all<T: Render + Any>(&mut self) -> Vec<&mut T> {
+        self.is_tree_changed |= self.refresh_root();
+        self.root.find_all()
+    }
+
+    /// Forgets the areas of the last render, e.g. because the terminal was
+    /// resized. Nothing will be found at any position until the next render.
+    pub fn invalidate_layout(&mut self) {
+        self.root.clear_areas()
+    }
+
+    /// Finds the components drawn at a position in the last render, in render
+    /// order.
+    ///
+    /// The areas are the ones recorded while rendering, so nothing is found
+    /// before the page is first rendered, nor after `invalidate_layout` or a
+    /// rebuild of the tree until the next render. This replaces the former
+    /// `components_at_position(pos, area) -> Vec<&RenderId>`, which laid the
+    /// tree out again in `area`: the area is now the one the page was last
+    /// rendered in, and the ids are returned by value.
+    pub fn components_at_position(&self, pos: &Position) -> Vec<RenderId> {
+        let mut found = vec![];
+        self.visit(&mut |details| {
+            if details.area().is_some_and(|area| area.contains(*pos)) {
+                found.push(details.id);
+            }
+            true
+        });
+        found
+    }
+
+    /// Delivers an event to the target component, letting it bubble up to the
+    /// page menu if nobody in the tree consumes it
+    pub(crate) fn dispatch_event(
+        &mut self,
+        target: Option<RenderId>,
+        event: &InputEvent,
+        alerts: &mut AlertManager,
+    ) -> EventResult {
+        let result = target
+            .and_then(|target| self.root.dispatch_event(&target, event, alerts))
+            .unwrap_or(EventResult::Ignored);
+        if result.is_consumed() {
+            result
+        } else {
+            self.menu.handle_event(alerts, event)
+        }
+    }
+
+    /// Rebuilds the invalidated factories, returning whether the tree changed
+    /// since the last call
+    pub(crate) fn refresh_factories(&mut self) -> bool {
+        let changed = self.refresh_root() | std::mem::take(&mut self.is_tree_changed);
+        if changed {
+            self.root.clear_areas()
+        }
+        changed
+    }
+
+    fn refresh_root(&mut self) -> bool {
+        let refresh = self.root.refresh_factories();
+        if let Some(key) = refresh.duplicate_key {
+            self.key_error = Some(self.duplicate_key_error(key));
+        }
+        refresh.changed
+    }
+
+    /// Delivers an event to a component of this page whether it is focused or
+    /// not, like the ticks of its timers. Returns `None` when it isn't found.
+    pub(crate) fn dispatch_to_component(
+        &mut self,
+        target: &RenderId,
+        event: &InputEvent,
+        alerts: &mut AlertManager,
+    ) -> Option<EventResult> {
+        self.root.dispatch_event(target, event, alerts)
     }
 
     pub(crate) fn get_active_element_menu(
@@ -124,7 +313,16 @@ impl RenderFlow for Page {
 
 #[cfg(test)]
 mod tests {
-    use crate::render::Render;
+    use ratatui::{
+        buffer::Buffer,
+        layout::{Constraint, Margin, Position, Rect},
+    };
+
+    use crate::{
+        core::{ComponentBuffer, LayoutNode, RenderComponent, RenderFlow, RenderId, VRenderProps},
+        render::{Render, RenderFactory, RenderProps},
+        windows::{alerts::AlertManager, page_collection::PageCollection},
+    };
 
     use super::Page;
 
@@ -140,6 +338,68 @@ mod tests {
             todo!()
         }
     }
+    struct EmptyWidget {}
+
+    impl Render for EmptyWidget {
+        fn render(&mut self, _render_props: &RenderProps, _buff: &mut Buffer, _area: Rect) {}
+    }
+
+    struct Counter(u32);
+
+    impl Render for Counter {
+        fn render(&mut self, _render_props: &RenderProps, _buff: &mut Buffer, _area: Rect) {}
+    }
+
+    #[test]
+    fn test_find_in_pages() {
+        let mut app = PageCollection::new(vec![
+            Page::new("One", '1', RenderComponent::new(Counter(1)).with_key("one")),
+            Page::new(
+                "Two",
+                '2',
+                RenderComponent::row(vec![
+                    RenderComponent::new(Counter(2)).with_key("two"),
+                    RenderComponent::new(EmptyWidget {}),
+                ]),
+            ),
+        ]);
+
+        app.find_by_key::<Counter>("two").unwrap().0 += 10;
+        assert!(app.find_by_key::<EmptyWidget>("two").is_none());
+        let counters: Vec<_> = app.find_all::<Counter>().into_iter().map(|c| c.0).collect();
+        assert_eq!(counters, vec![1, 12]);
+
+        let page = app.page_mut('2').unwrap();
+        let mut id = None;
+        page.visit_mut(&mut |details| {
+            id = Some(details.id);
+            false
+        });
+        assert_eq!(page.find_by_id::<Counter>(&id.unwrap()).unwrap().0, 12);
+        assert_eq!(page.find_all::<EmptyWidget>().len(), 1);
+    }
+
+    fn render(page: &mut Page, area: Rect) {
+        page.render(
+            &mut VRenderProps {
+                focused_element: None,
+                alerts: &mut AlertManager::default(),
+            },
+            &mut ComponentBuffer::default(),
+            &mut Buffer::empty(area),
+            area,
+        );
+    }
+
+    fn ids(page: &Page) -> Vec<RenderId> {
+        let mut ids = vec![];
+        page.visit(&mut |details| {
+            ids.push(details.id);
+            true
+        });
+        ids
+    }
+
     #[test]
     fn test_creating_page() {
         let _page = Page::new("P1", 'p', MyWidget {});
@@ -149,4 +409,182 @@ mod tests {
             |_: super::MenuEvent<'_>| {},
         )]);
     }
+
+    #[test]
+    fn test_components_at_position_with_constraints() {
+        let mut page = Page::new(
+            "P1",
+            'p',
+            RenderComponent::row_with_constraints(vec![
+                (Constraint::Length(30), RenderComponent::new(EmptyWidget {})),
+                (Constraint::Fill(1), RenderComponent::new(EmptyWidget {})),
+            ]),
+        );
+        let ids = ids(&page);
+        render(&mut page, Rect::new(0, 0, 100, 20));
+
+        assert_eq!(
+            page.components_at_position(&Position::new(29, 10)),
+            vec![ids[0]]
+        );
+        assert_eq!(
+            page.components_at_position(&Position::new(30, 10)),
+            vec![ids[1]]
+        );
+    }
+
+    #[test]
+    fn test_components_at_position_with_spacing() {
+        let mut page = Page::new(
+            "P1",
+            'p',
+            LayoutNode::row(vec![
+                RenderComponent::new(EmptyWidget {}),
+                RenderComponent::new(EmptyWidget {}),
+            ])
+            .with_margin(Margin::new(1, 1))
+            .with_spacing(2),
+        );
+        let ids = ids(&page);
+        render(&mut page, Rect::new(0, 0, 22, 10));
+
+        assert!(page.components_at_position(&Position::new(0, 5)).is_empty());
+        assert_eq!(
+            page.components_at_position(&Position::new(1, 5)),
+            vec![ids[0]]
+        );
+        assert!(page
+            .components_at_position(&Position::new(10, 5))
+            .is_empty());
+        assert_eq!(
+            page.components_at_position(&Position::new(12, 5)),
+            vec![ids[1]]
+        );
+    }
+
+    #[test]
+    fn test_components_at_position_before_render() {
+        let page = Page::new("P1", 'p', row_widget!(EmptyWidget {}, EmptyWidget {}));
+
+        assert!(page.components_at_position(&Position::new(0, 0)).is_empty());
+    }
+
+    #[test]
+    fn test_components_at_position_in_grid() {
+        let mut page = Page::new(
+            "P1",
+            'p',
+            column_widget!(
+                row_widget!(EmptyWidget {}, EmptyWidget {}),
+                row_widget!(EmptyWidget {}, EmptyWidget {}),
+            ),
+        );
+        let ids = ids(&page);
+        render(&mut page, Rect::new(0, 0, 20, 10));
+
+        // top-left, top-right, bottom-left, bottom-right
+        let cells = [(5, 2), (15, 2), (5, 7), (15, 7)];
+        for (id, (x, y)) in ids.iter().zip(cells) {
+            assert_eq!(page.components_at_position(&Position::new(x, y)), vec![*id]);
+        }
+    }
+
+    struct MyFactory {}
+
+    impl RenderFactory for MyFactory {
+        fn render(&mut self) -> RenderComponent {
+            column_widget!(EmptyWidget {}, EmptyWidget {}).into()
+        }
+    }
+
+    #[test]
+    fn test_components_at_position_with_factory() {
+        let mut page = Page::new(
+            "P1",
+            'p',
+            column_widget!(
+                EmptyWidget {},
+                row_widget!(EmptyWidget {}, RenderComponent::new_factory(MyFactory {})),
+            ),
+        );
+        let ids = ids(&page);
+        render(&mut page, Rect::new(0, 0, 20, 20));
+
+        assert_eq!(
+            page.components_at_position(&Position::new(5, 15)),
+            vec![ids[1]]
+        );
+        assert_eq!(
+            page.components_at_position(&Position::new(15, 12)),
+            vec![ids[2]]
+        );
+        assert_eq!(
+            page.components_at_position(&Position::new(15, 17)),
+            vec![ids[3]]
+        );
+    }
+
+    struct CounterFactory {}
+
+    impl RenderFactory for CounterFactory {
+        fn render(&mut self) -> RenderComponent {
+            RenderComponent::new(Counter(0))
+        }
+    }
+
+    #[test]
+    fn test_edits_survive_invalidated_factories() {
+        let factory = RenderComponent::new_factory(CounterFactory {});
+        let handle = factory.factory_handle().unwrap();
+        let mut page = Page::new("P1", 'p', factory);
+        assert_eq!(page.find_all::<Counter>().len(), 1);
+        page.refresh_factories();
+
+        handle.invalidate();
+        page.find_all::<Counter>()[0].0 += 5;
+        assert!(page.refresh_factories());
+        assert_eq!(page.find_all::<Counter>()[0].0, 5);
+    }
+
+    /// Uses the same key twice from its second build on
+    struct DuplicatingFactory {
+        builds: usize,
+    }
+
+    impl RenderFactory for DuplicatingFactory {
+        fn render(&mut self) -> RenderComponent {
+            self.builds += 1;
+            RenderComponent::row(
+                (0..self.builds)
+                    .map(|_| RenderComponent::new(Counter(0)).with_key("counter"))
+                    .collect(),
+            )
+        }
+    }
+
+    #[test]
+    fn test_lookups_record_duplicate_keys() {
+        let factory = RenderComponent::new_factory(DuplicatingFactory { builds: 0 });
+        let handle = factory.factory_handle().unwrap();
+        let mut page = Page::new("P1", 'p', factory);
+        assert_eq!(page.find_all::<Counter>().len(), 1);
+        assert!(page.take_key_error().is_none());
+
+        handle.invalidate();
+        assert!(page.find_by_key::<Counter>("counter").is_some());
+        let err = page.take_key_error().unwrap();
+        assert_eq!((err.page.as_str(), err.key.as_str()), ("P1", "counter"));
+        // the previous tree is kept
+        assert_eq!(page.find_all::<Counter>().len(), 1);
+    }
+
+    #[test]
+    fn test_visit_mut_changes_the_tree() {
+        let mut page = Page::new("P1", 'p', RenderComponent::new(Counter(0)));
+        page.refresh_factories();
+        assert!(!page.refresh_factories());
+
+        page.visit_mut(&mut |_| true);
+        assert!(page.refresh_factories());
+    }
 }
@@ -1,4 +1,9 @@
-use crate::{core::RenderFlow, core::RenderId};
+use std::any::Any;
+
+use crate::{
+    core::{RenderFlow, RenderId, RenderNode},
+    render::Render,
+};
 
 use super::{
     menu::{Menu, MenuEvent},
@@ -30,6 +35,34 @@ impl PageCollection {
         self.pages.get(self.current_page).unwrap()
     }
 
+    /// The page with the given shortcut, e.g. to replace its components
+    pub fn page_mut(&mut self, shortcut: char) -> Option<&mut Page> {
+        self.pages.iter_mut().find(|p| p.shortcut == shortcut)
+    }
+
+    /// Visits the components of every page, until `f` returns `false`
+    pub fn visit_mut(&mut self, f: &mut dyn FnMut(&mut RenderNode) -> bool) -> bool {
+        self.pages.iter_mut().all(|page| page.visit_mut(f))
+    }
+
+    /// Looks for the component in every page, see `Page::find_by_id`
+    pub fn find_by_id<T: Render + Any>(&mut self, id: &RenderId) -> Option<&mut T> {
+        self.pages.iter_mut().find_map(|page| page.find_by_id(id))
+    }
+
+    /// Looks for the component in every page, see `Page::find_by_key`
+    pub fn find_by_key<T: Render + Any>(&mut self, key: &str) -> Option<&mut T> {
+        self.pages.iter_mut().find_map(|page| page.find_by_key(key))
+    }
+
+    /// Every widget that is a `T` in every page, in page order
+    pub fn find_all<T: Render + Any>(&mut self) -> Vec<&mut T> {
+        self.pages
+            .iter_mut()
+            .flat_map(|page| page.find_all())
+            .collect()
+    }
+
     pub(crate) fn try_change_page(&mut self, shortcut: char) -> bool {
         let maybe_new_page = self.pages.iter().enumerate().find_map(|(i, p)| {
             if p.shortcut == shortcut {
@@ -48,6 +81,13 @@ impl PageCollection {
         }
     }
 
+    /// Forgets the areas of the last render in every page
+    pub fn invalidate_layout(&mut self) {
+        for page in &mut self.pages {
+            page.invalidate_layout()
+        }
+    }
+
     pub fn get_menu(&self, focused_element: &Option<RenderId>) -> Option<Menu> {
         let active_component_menu = self
             .get_current_page()
@@ -60,6 +100,8 @@ impl PageCollection {
                 .collect(),
         );
 
+        pages_menu.append(self.get_current_page().menu.clone());
+
         if let Some(cmenu) = active_component_menu {
             pages_menu.append(cmenu.clone());
         }
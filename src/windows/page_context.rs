@@ -38,6 +38,16 @@ impl PageContext {
         }
     }
 
+    /// Recomputes the focusable elements after the page tree changed, keeping
+    /// the focus if the focused element is still there
+    pub(crate) fn refresh_focusable_elements(&mut self, page: &Page) {
+        let focused = self.get_focused_element();
+        self.focusable_elements = Self::build_focusable_elements(&self.id, page);
+        if let Some(focused) = focused {
+            self.focusable_elements.select(&focused);
+        }
+    }
+
     pub(crate) fn is_window_focused(&self) -> bool {
         self.focusable_elements
             .current()
@@ -53,6 +63,23 @@ impl PageContext {
         self.focusable_elements.move_previous();
     }
 
+    /// Focuses the given element, if it is focusable in this page
+    pub(crate) fn focus(&mut self, id: &RenderId) -> bool {
+        self.focusable_elements.select(id)
+    }
+
+    /// Focuses the first focusable component of the page, if there is any
+    pub(crate) fn focus_first(&mut self) -> bool {
+        // the window itself comes first
+        self.focusable_elements.select_index(1)
+    }
+
+    /// Focuses the last focusable component of the page, if there is any
+    pub(crate) fn focus_last(&mut self) -> bool {
+        let len = self.focusable_elements.len();
+        len > 1 && self.focusable_elements.select_index(len - 1)
+    }
+
     pub(crate) fn get_focused_element(&self) -> Option<RenderId> {
         self.focusable_elements.current().cloned()
     }
@@ -1,27 +1,89 @@
-use std::time::Duration;
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError},
+    },
+    thread,
+    time::{Duration, Instant},
+};
 
-use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers, MouseEventKind};
 use ratatui::{
+    backend::Backend,
     buffer::Buffer,
     layout::{Constraint, Direction, Layout, Position, Rect},
     text::Text,
     widgets::Widget,
+    Terminal,
 };
 
 use crate::{
     core::RenderId,
     core::{InputEvent, RenderFlow, VRenderProps},
+    events::{pump_events, CrosstermEventSource, EventSource, LoopMessage, RedrawHandle},
     utils::SelectableHashMap,
 };
 
+pub use crate::events::{DefaultEventMapper, EventMapper};
+
 use super::{
     alerts::AlertManager,
+    focus::{FocusHandle, FocusRequest},
     menu::{Menu, MenuItem},
-    page::Page,
+    page::{DuplicateKeyError, Page},
     page_collection::PageCollection,
     page_context::PageContext,
 };
 
+/// The maximum frame rate of `Window::run`, by default
+pub const DEFAULT_MAX_FPS: u32 = 60;
+
+/// Paces the frames drawn by `Window::run_with`. A frame is drawn after events,
+/// redraw requests, timer ticks and while an alert counts down, but never more
+/// often than the maximum frame rate. There are no periodic ticks by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RunOptions {
+    tick_rate: Option<Duration>,
+    max_fps: Option<u32>,
+}
+
+impl Default for RunOptions {
+    fn default() -> Self {
+        Self {
+            tick_rate: None,
+            max_fps: Some(DEFAULT_MAX_FPS),
+        }
+    }
+}
+
+impl RunOptions {
+    /// Draws at least once per `tick_rate`, or only when needed with `None`
+    /// (the default)
+    pub fn with_tick_rate(mut self, tick_rate: Option<Duration>) -> Self {
+        self.tick_rate = tick_rate;
+        self
+    }
+
+    /// Limits the frames per second, or draws as often as needed with `None`
+    pub fn with_max_fps(mut self, max_fps: Option<u32>) -> Self {
+        self.max_fps = max_fps;
+        self
+    }
+
+    fn frame_interval(&self) -> Option<Duration> {
+        self.max_fps.map(|fps| Duration::from_secs(1) / fps.max(1))
+    }
+}
+
+/// Why `Window::run` returned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitReason {
+    /// The end condition of the window was met
+    EndCondition,
+    /// The event source won't produce any more events
+    SourceClosed,
+}
+
 struct WindowRenderer {}
 
 // TODO: Too ugly, lets improve this. Make it extensible
@@ -108,6 +170,7 @@ pub struct Window {
     is_ended: bool,
     page_context_map: SelectableHashMap<RenderId, PageContext>,
     alerts: AlertManager,
+    redraw: RedrawHandle,
 }
 
 enum WindowEventResult {
@@ -116,6 +179,8 @@ enum WindowEventResult {
 }
 
 impl Window {
+    /// Creates a window for the pages of `app`, ending when `end_condition`
+    /// matches an event. The keys of the pages aren't checked, see `Window::try_new`.
     pub fn new<F: Fn(&InputEvent) -> bool + 'static>(
         app: &PageCollection,
         end_condition: F,
@@ -134,7 +199,26 @@ impl Window {
                     .collect(),
             ),
             alerts: AlertManager::default(),
+            redraw: RedrawHandle::default(),
+        }
+    }
+
+    /// Like `Window::new`, failing if a page uses a key more than once
+    pub fn try_new<F: Fn(&InputEvent) -> bool + 'static>(
+        app: &PageCollection,
+        end_condition: F,
+    ) -> Result<Self, DuplicateKeyError> {
+        for page in &app.pages {
+            page.check_keys()?;
         }
+        Ok(Self::new(app, end_condition))
+    }
+
+    /// A handle to ask the running loop for a new frame, e.g. from a background
+    /// thread. While handling events and drawing it is also available through
+    /// `RedrawHandle::current`.
+    pub fn redraw_handle(&self) -> RedrawHandle {
+        self.redraw.clone()
     }
 
     fn is_window_focused(&self) -> bool {
@@ -144,6 +228,17 @@ impl Window {
             .unwrap_or(false)
     }
 
+    /// Rebuilds the invalidated factories of the current page, refreshing what
+    /// can be focused
+    fn refresh_current_page(&mut self, app: &mut PageCollection) {
+        let page = app.get_current_page_mut();
+        if page.refresh_factories() {
+            if let Some(context) = self.page_context_map.get_mut(page.get_page_id()) {
+                context.refresh_focusable_elements(page)
+            }
+        }
+    }
+
     fn on_page_change(&mut self, page: &PageCollection) {
         let new_page = page.get_current_page();
         if let Some(new_context) = self.page_context_map.get_mut(new_page.get_page_id()) {
@@ -152,18 +247,24 @@ impl Window {
         self.page_context_map.set_current(*new_page.get_page_id())
     }
 
+    /// Window-level shortcuts, reached by the events nobody else consumed
     fn handle_window_event(
         &mut self,
         ev: &InputEvent,
         pages: &mut PageCollection,
     ) -> WindowEventResult {
-        if self.is_window_focused() {
-            self.is_ended = (self.end_condition)(ev);
+        // like the shortcuts shown in the footer, the end condition and the
+        // page shortcuts only apply while the window itself has the focus
+        let is_window_focused = self.is_window_focused();
+        if is_window_focused && (self.end_condition)(ev) {
+            self.is_ended = true;
         }
         match ev {
-            InputEvent::Key(c) => {
-                if self.is_window_focused() && pages.try_change_page(*c) {
-                    return WindowEventResult::PageChange;
+            InputEvent::Key(key) => {
+                if let Some(c) = key.as_char() {
+                    if is_window_focused && pages.try_change_page(c) {
+                        return WindowEventResult::PageChange;
+                    }
                 }
             }
             InputEvent::FocusNext => {
@@ -171,24 +272,152 @@ impl Window {
                     p.focus_next()
                 }
             }
-            InputEvent::FocusWindow => {
-                if let Some(p) = self.page_context_map.get_current_mut() {
-                    p.reset_focus()
-                }
-            }
+            InputEvent::FocusWindow => self.focus_window(),
             InputEvent::FocusPrevious => {
                 if let Some(p) = self.page_context_map.get_current_mut() {
                     p.focus_prev()
                 }
             }
-            InputEvent::Click(_) => {
-                // TODO
-            }
+            _ => {}
         };
 
         WindowEventResult::None
     }
 
+    fn get_focused_element(&self) -> Option<RenderId> {
+        self.page_context_map
+            .get_current()
+            .and_then(|p| p.get_focused_element())
+    }
+
+    /// The focused component of the current page, or `None` when the focus is
+    /// on the window itself
+    pub fn focused(&self) -> Option<RenderId> {
+        if self.is_window_focused() {
+            None
+        } else {
+            self.get_focused_element()
+        }
+    }
+
+    /// Focuses a component of the current page. Returns whether it was found
+    /// and can be focused; the focus doesn't move otherwise.
+    pub fn focus(&mut self, id: &RenderId) -> bool {
+        self.page_context_map
+            .get_current_mut()
+            .is_some_and(|p| p.focus(id))
+    }
+
+    /// Focuses the component of the current page with the given key, once its
+    /// invalidated factories are rebuilt. Returns whether it was found and can
+    /// be focused.
+    pub fn focus_key(&mut self, app: &mut PageCollection, key: &str) -> bool {
+        self.refresh_current_page(app);
+        app.get_current_page()
+            .id_of(key)
+            .is_some_and(|id| self.focus(&id))
+    }
+
+    /// Focuses the first focusable component of the current page, returning
+    /// whether there is one
+    pub fn focus_first(&mut self) -> bool {
+        self.page_context_map
+            .get_current_mut()
+            .is_some_and(|p| p.focus_first())
+    }
+
+    /// Focuses the last focusable component of the current page, returning
+    /// whether there is one
+    pub fn focus_last(&mut self) -> bool {
+        self.page_context_map
+            .get_current_mut()
+            .is_some_and(|p| p.focus_last())
+    }
+
+    /// Gives the focus back to the window, as with Esc
+    pub fn focus_window(&mut self) {
+        if let Some(p) = self.page_context_map.get_current_mut() {
+            p.reset_focus()
+        }
+    }
+
+    /// Moves the focus as asked, e.g. through a `FocusHandle`, in the current
+    /// page with its invalidated factories rebuilt. Returns whether it moved.
+    pub fn request_focus(&mut self, app: &mut PageCollection, request: FocusRequest) -> bool {
+        self.refresh_current_page(app);
+        match request {
+            FocusRequest::Id(id) => self.focus(&id),
+            FocusRequest::Key(key) => self.focus_key(app, &key),
+            FocusRequest::First => self.focus_first(),
+            FocusRequest::Last => self.focus_last(),
+            FocusRequest::Window => {
+                self.focus_window();
+                true
+            }
+        }
+    }
+
+    #[cfg(any(test, feature = "testing"))]
+    pub(crate) fn alerts_mut(&mut self) -> &mut AlertManager {
+        &mut self.alerts
+    }
+
+    /// Delivers an event to the focused component (or the one under the pointer),
+    /// letting it bubble up through the layouts containing it, the page menu and
+    /// the window shortcuts until someone consumes it. Returns the component the
+    /// event was targeted at.
+    fn dispatch_event(&mut self, ev: &InputEvent, app: &mut PageCollection) -> Option<RenderId> {
+        match ev {
+            InputEvent::Click(position) => self.focus_at_position(position, app),
+            InputEvent::Resize(_, _) => app.invalidate_layout(),
+            _ => {}
+        }
+
+        let target =
+            Self::get_event_target(ev, &self.get_focused_element(), app.get_current_page());
+        let result = app
+            .get_current_page_mut()
+            .dispatch_event(target, ev, &mut self.alerts);
+
+        if !result.is_consumed() {
+            match self.handle_window_event(ev, app) {
+                WindowEventResult::PageChange => self.on_page_change(app),
+                WindowEventResult::None => {}
+            };
+        }
+        target
+    }
+
+    /// Focuses the topmost focusable component under `position`, as drawn in the
+    /// last render.
+    fn focus_at_position(&mut self, position: &Position, pages: &PageCollection) {
+        if let Some(p) = self.page_context_map.get_current_mut() {
+            pages
+                .get_current_page()
+                .components_at_position(position)
+                .into_iter()
+                .rev()
+                .any(|id| p.focus(&id));
+        }
+    }
+
+    /// Pointer events go to the topmost component under the pointer, resizes to
+    /// nobody and everything else to the focused component.
+    fn get_event_target(
+        ev: &InputEvent,
+        focused_element: &Option<RenderId>,
+        current_page: &Page,
+    ) -> Option<RenderId> {
+        match (ev, ev.position()) {
+            (_, Some(position)) => current_page
+                .components_at_position(&position)
+                .last()
+                .copied(),
+            (InputEvent::Resize(_, _), _) => None,
+            _ => *focused_element,
+        }
+    }
+
     fn get_active_element_menu(
         focused_element: &Option<RenderId>,
         current_page: &Page,
@@ -215,33 +444,55 @@ impl Window {
         }
     }
 
-    pub fn render<T: EventMapper>(
-        &mut self,
-        app: &mut PageCollection,
-        buff: &mut Buffer,
-        area: Rect,
-    ) {
-        let event = get_event::<T>();
-
-        let focused_element = if let Some(page) = self.page_context_map.get_current_mut() {
-            let focused_element = page.get_focused_element();
+    /// Handles an event: the focused component (or the one under the pointer)
+    /// gets it first and whatever it ignores bubbles up to the window shortcuts.
+    pub fn handle_event(&mut self, app: &mut PageCollection, event: InputEvent) {
+        let _redraw = self.redraw.clone().enter();
+        self.refresh_current_page(app);
+        let focus = FocusHandle::enter(self.focused());
+        let event_target = self.dispatch_event(&event, app);
+        if let Some(request) = focus.handle.take_request() {
+            // the handler may have changed the tree the focus should move in,
+            // which the request sees
+            self.request_focus(app, request);
+        }
+        if let Some(page) = self.page_context_map.get_current_mut() {
             page.event_buffer
-                .add_event(focused_element.unwrap_or(self.id), &event);
-
-            focused_element
-        } else {
-            None
-        };
-        if let Some(ev) = &event {
-            match self.handle_window_event(ev, app) {
-                WindowEventResult::PageChange => self.on_page_change(app),
-                WindowEventResult::None => {}
-            };
+                .add_event(event_target.unwrap_or(self.id), &Some(event));
+        }
+    }
 
-            if let Some(mut menu) = app.get_menu(&focused_element) {
-                menu.handle_event(&mut self.alerts, ev)
+    /// Delivers the ticks of the timers that fired since the last call to their
+    /// components, on any page. Returns whether there were any. Only the
+    /// components of the current page can move the focus.
+    pub fn handle_timer_ticks(&mut self, app: &mut PageCollection) -> bool {
+        let ticks = self.redraw.take_ticks();
+        let _redraw = self.redraw.clone().enter();
+        let focus = FocusHandle::enter(self.focused());
+        let current_page = *app.get_current_page().get_page_id();
+        let mut request = None;
+        for (target, timer) in &ticks {
+            let page = app.pages.iter_mut().find_map(|page| {
+                page.dispatch_to_component(target, &InputEvent::Tick(*timer), &mut self.alerts)
+                    .map(|_| *page.get_page_id())
+            });
+            let asked = focus.handle.take_request();
+            if page == Some(current_page) && asked.is_some() {
+                request = asked;
             }
         }
+        if let Some(request) = request {
+            self.request_focus(app, request);
+        }
+        !ticks.is_empty()
+    }
+
+    /// Draws the current page. Events are handled separately by `handle_event`,
+    /// so this can be called as often as needed.
+    pub fn render(&mut self, app: &mut PageCollection, buff: &mut Buffer, area: Rect) {
+        let _redraw = self.redraw.clone().enter();
+        self.refresh_current_page(app);
+        let focused_element = self.get_focused_element();
 
         let current_page_style = app.get_current_page().style;
         buff.set_style(area, current_page_style);
@@ -264,7 +515,6 @@ impl Window {
             &mut VRenderProps {
                 alerts: &mut self.alerts,
                 focused_element,
-                event,
             },
             event_buffer,
             buff,
@@ -277,51 +527,1120 @@ impl Window {
     pub fn is_finished(&self) -> bool {
         self.is_ended
     }
-}
 
-pub trait EventMapper {
-    fn to_input_event(ev: &crossterm::event::Event) -> Option<InputEvent>;
+    /// Runs the application on a terminal with the events read from Crossterm,
+    /// until the end condition is met
+    pub fn run<B: Backend>(
+        &mut self,
+        app: &mut PageCollection,
+        terminal: &mut Terminal<B>,
+    ) -> io::Result<ExitReason> {
+        self.run_with(
+            app,
+            terminal,
+            &mut CrosstermEventSource::<DefaultEventMapper>::new(),
+            RunOptions::default(),
+        )
+    }
+
+    /// Runs the application with any event source. The source is read on its own
+    /// thread, so the loop sleeps until an event, a redraw request or a tick.
+    pub fn run_with<B: Backend, S: EventSource + Send>(
+        &mut self,
+        app: &mut PageCollection,
+        terminal: &mut Terminal<B>,
+        events: &mut S,
+        options: RunOptions,
+    ) -> io::Result<ExitReason> {
+        // a single slot, so that the source isn't read far ahead of the loop
+        let (tx, rx) = mpsc::sync_channel(1);
+        let stop = AtomicBool::new(false);
+        self.redraw.set_waker(Some(tx.clone()));
+
+        let result = thread::scope(|scope| {
+            scope.spawn(|| pump_events(events, tx, &stop));
+            let result = self.run_loop(app, terminal, rx, options);
+            stop.store(true, Ordering::Relaxed);
+            result
+        });
+
+        self.redraw.set_waker(None);
+        result
+    }
+
+    fn run_loop<B: Backend>(
+        &mut self,
+        app: &mut PageCollection,
+        terminal: &mut Terminal<B>,
+        rx: Receiver<LoopMessage>,
+        options: RunOptions,
+    ) -> io::Result<ExitReason> {
+        let mut next_tick = options.tick_rate.map(|rate| Instant::now() + rate);
+        let mut last_frame: Option<Instant> = None;
+        let mut is_dirty = true;
+        let mut is_closed = false;
+
+        loop {
+            is_dirty |= self.redraw.take_request();
+            is_dirty |= self.handle_timer_ticks(app);
+            let now = Instant::now();
+            let next_frame = last_frame
+                .zip(options.frame_interval())
+                .map_or(now, |(last, interval)| last + interval);
+
+            // the last frame is drawn right away when the source closes
+            if is_dirty && (now >= next_frame || is_closed) {
+                terminal.draw(|f| {
+                    let area = f.size();
+                    self.render(app, f.buffer_mut(), area)
+                })?;
+                last_frame = Some(now);
+                is_dirty = false;
+            }
+
+            if self.is_finished() {
+                return Ok(ExitReason::EndCondition);
+            }
+            if is_closed {
+                return Ok(ExitReason::SourceClosed);
+            }
+
+            let alert_redraw = self.alerts.next_redraw();
+            let deadline = [next_tick, is_dirty.then_some(next_frame), alert_redraw]
+                .into_iter()
+                .flatten()
+                .min();
+            let message = match deadline {
+                Some(deadline) => rx.recv_timeout(deadline.saturating_duration_since(now)),
+                None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+
+            match message {
+                Ok(LoopMessage::Event(event)) => {
+                    if let InputEvent::Resize(..) = event {
+                        terminal.autoresize()?;
+                    }
+                    self.handle_event(app, event);
+                    is_dirty = true;
+                }
+                Ok(LoopMessage::Redraw) | Err(RecvTimeoutError::Timeout) => {}
+                Ok(LoopMessage::Closed) | Err(RecvTimeoutError::Disconnected) => is_closed = true,
+                Ok(LoopMessage::Error(e)) => return Err(e),
+            }
+
+            let now = Instant::now();
+            if alert_redraw.is_some_and(|redraw| now >= redraw) {
+                is_dirty = true;
+            }
+            if let (Some(tick), Some(rate)) = (next_tick, options.tick_rate) {
+                if now >= tick {
+                    is_dirty = true;
+                    next_tick = Some(now + rate);
+                }
+            }
+        }
+    }
 }
 
-#[derive(Default)]
-pub struct DefaultEventMapper {}
+#[cfg(test)]
+mod tests {
+    use ratatui::{
+        buffer::Buffer,
+        layout::{Position, Rect},
+        style::Style,
+    };
 
-impl EventMapper for DefaultEventMapper {
-    fn to_input_event(ev: &crossterm::event::Event) -> Option<InputEvent> {
-        match ev {
-            Event::FocusGained => None,
-            Event::FocusLost => None,
-            Event::Key(KeyEvent {
-                modifiers: KeyModifiers::SHIFT,
-                code: KeyCode::Tab,
-                ..
-            }) => Some(InputEvent::FocusPrevious),
-            Event::Key(key) => match key.code {
-                KeyCode::Char(c) => Some(InputEvent::Key(c)),
-                KeyCode::Tab => Some(InputEvent::FocusNext),
-                KeyCode::Esc => Some(InputEvent::FocusWindow),
-                _ => None,
+    use crate::{
+        core::{ComponentBuffer, InputEvent, RenderComponent, RenderFlow, RenderId, VRenderProps},
+        render::{FocusableRender, Render, RenderProps},
+        windows::{alerts::AlertManager, page::Page, page_collection::PageCollection},
+    };
+
+    use std::{
+        cell::Cell,
+        cell::RefCell,
+        rc::Rc,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        thread,
+        time::Duration,
+    };
+
+    use ratatui::{backend::TestBackend, Terminal};
+
+    use super::{ExitReason, RunOptions, Window};
+    use crate::{
+        core::{FactoryHandle, KeyCode, KeyEvent, KeyModifiers, ScrollDirection},
+        events::{ChannelEventSource, EventSource, ScriptedEventSource},
+        hooks::{ManualClock, Timer},
+        render::RenderFactory,
+        testing::TestDriver,
+        utils::wait_until,
+    };
+    use crate::{
+        render::{EventContext, EventResult},
+        windows::menu::{Menu, MenuEvent},
+    };
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    struct Focusable {}
+
+    impl FocusableRender for Focusable {
+        fn render(&mut self, _render_props: &RenderProps, _buff: &mut Buffer, _area: Rect) {}
+    }
+
+    struct Static {}
+
+    impl Render for Static {
+        fn render(&mut self, _render_props: &RenderProps, _buff: &mut Buffer, _area: Rect) {}
+    }
+
+    #[test]
+    fn test_click_focuses_component() {
+        let mut app = PageCollection::new(vec![Page::new(
+            "Page",
+            'p',
+            row_widget!(Focusable {}, Focusable {}, Static {}),
+        )]);
+        let mut ids = vec![];
+        app.get_current_page().visit(&mut |details| {
+            ids.push(details.id);
+            true
+        });
+        let mut window = Window::new(&app, |_| false);
+        let area = Rect::new(0, 0, 30, 10);
+        app.render(
+            &mut VRenderProps {
+                focused_element: None,
+                alerts: &mut AlertManager::default(),
             },
-            Event::Mouse(mouse_event) => match mouse_event.kind {
-                MouseEventKind::Up(_) => Some(InputEvent::Click(Position::new(
-                    mouse_event.column,
-                    mouse_event.row,
-                ))),
-                _ => None,
+            &mut ComponentBuffer::default(),
+            &mut Buffer::empty(area),
+            area,
+        );
+
+        let focused = |window: &Window| {
+            window
+                .page_context_map
+                .get_current()
+                .and_then(|p| p.get_focused_element())
+        };
+
+        window.dispatch_event(&InputEvent::Click(Position::new(15, 5)), &mut app);
+        assert_eq!(focused(&window), Some(ids[1]));
+
+        window.dispatch_event(&InputEvent::Click(Position::new(2, 2)), &mut app);
+        assert_eq!(focused(&window), Some(ids[0]));
+
+        // static components can't take the focus
+        window.dispatch_event(&InputEvent::Click(Position::new(25, 5)), &mut app);
+        assert_eq!(focused(&window), Some(ids[0]));
+    }
+
+    #[test]
+    fn test_click_each_cell_of_grid() {
+        let mut app = PageCollection::new(vec![Page::new(
+            "Page",
+            'p',
+            column_widget!(
+                row_widget!(Focusable {}, Focusable {}),
+                row_widget!(Focusable {}, Focusable {}),
+            ),
+        )]);
+        let ids = app.get_focusable_elements();
+        let mut window = Window::new(&app, |_| false);
+        let area = Rect::new(0, 0, 20, 10);
+        app.render(
+            &mut VRenderProps {
+                focused_element: None,
+                alerts: &mut AlertManager::default(),
             },
-            Event::Paste(_) => None,
-            Event::Resize(_, _) => None,
+            &mut ComponentBuffer::default(),
+            &mut Buffer::empty(area),
+            area,
+        );
+
+        // top-left, top-right, bottom-left, bottom-right
+        let cells = [(5, 2), (15, 2), (5, 7), (15, 7)];
+        for (id, (x, y)) in ids.iter().zip(cells) {
+            window.dispatch_event(&InputEvent::Click(Position::new(x, y)), &mut app);
+            assert_eq!(
+                window
+                    .page_context_map
+                    .get_current()
+                    .and_then(|p| p.get_focused_element()),
+                Some(*id)
+            );
         }
     }
-}
 
-pub fn get_event<T: EventMapper>() -> Option<InputEvent> {
-    if let Ok(has_ev) = event::poll(Duration::from_millis(250)) {
-        if has_ev {
-            if let Ok(ev) = event::read() {
-                return T::to_input_event(&ev);
+    #[test]
+    fn test_page_shortcut_with_key_event() {
+        let mut app = PageCollection::new(vec![
+            Page::new("Page 1", '1', Focusable {}),
+            Page::new("Page 2", '2', Static {}),
+        ]);
+        let mut window = Window::new(&app, |_| false);
+
+        window.dispatch_event(
+            &InputEvent::Key(KeyEvent::new(KeyCode::Char('2'), KeyModifiers::CONTROL)),
+            &mut app,
+        );
+        assert_eq!(app.get_current_page().shortcut, '1');
+
+        // ignored by the focused component, but page shortcuts need the window
+        window.dispatch_event(&InputEvent::FocusNext, &mut app);
+        window.dispatch_event(&InputEvent::Key('2'.into()), &mut app);
+        assert_eq!(app.get_current_page().shortcut, '1');
+
+        window.dispatch_event(&InputEvent::FocusWindow, &mut app);
+        window.dispatch_event(&InputEvent::Key('2'.into()), &mut app);
+        assert_eq!(app.get_current_page().shortcut, '2');
+    }
+
+    #[test]
+    fn test_event_targets() {
+        let mut app = PageCollection::new(vec![Page::new(
+            "Page",
+            'p',
+            row_widget!(Focusable {}, Static {}),
+        )]);
+        let mut ids = vec![];
+        app.get_current_page().visit(&mut |details| {
+            ids.push(details.id);
+            true
+        });
+        let area = Rect::new(0, 0, 20, 10);
+        app.render(
+            &mut VRenderProps {
+                focused_element: None,
+                alerts: &mut AlertManager::default(),
+            },
+            &mut ComponentBuffer::default(),
+            &mut Buffer::empty(area),
+            area,
+        );
+        let focused = Some(ids[0]);
+        let target = |ev| Window::get_event_target(&ev, &focused, app.get_current_page());
+
+        assert_eq!(
+            target(InputEvent::Scroll(
+                ScrollDirection::Up,
+                Position::new(15, 5)
+            )),
+            Some(ids[1])
+        );
+        assert_eq!(target(InputEvent::Drag(Position::new(15, 5))), Some(ids[1]));
+        assert_eq!(target(InputEvent::Paste("text".into())), Some(ids[0]));
+        assert_eq!(target(InputEvent::Resize(10, 10)), None);
+    }
+
+    #[test]
+    fn test_resize_invalidates_layout() {
+        let mut app = PageCollection::new(vec![Page::new("Page", 'p', Focusable {})]);
+        let mut window = Window::new(&app, |_| false);
+        let area = Rect::new(0, 0, 20, 10);
+        app.render(
+            &mut VRenderProps {
+                focused_element: None,
+                alerts: &mut AlertManager::default(),
+            },
+            &mut ComponentBuffer::default(),
+            &mut Buffer::empty(area),
+            area,
+        );
+        let position = Position::new(15, 5);
+        assert_eq!(
+            app.get_current_page()
+                .components_at_position(&position)
+                .len(),
+            1
+        );
+
+        window.dispatch_event(&InputEvent::Resize(10, 10), &mut app);
+        assert!(app
+            .get_current_page()
+            .components_at_position(&position)
+            .is_empty());
+    }
+
+    #[derive(Default)]
+    struct TextBox {
+        text: String,
+    }
+
+    impl FocusableRender for TextBox {
+        fn render(&mut self, _render_props: &RenderProps, _buff: &mut Buffer, _area: Rect) {}
+
+        fn on_event(&mut self, event: &InputEvent, _ctx: &mut EventContext) -> EventResult {
+            match event.as_char() {
+                Some(c) if c.is_alphanumeric() => {
+                    self.text.push(c);
+                    EventResult::Consumed
+                }
+                _ => EventResult::Ignored,
+            }
+        }
+
+        fn get_menu(&self) -> Option<Menu> {
+            Some(Menu::from_entries(vec![(
+                '/',
+                "Search",
+                |ev: MenuEvent| ev.alerts.alert("search"),
+            )]))
+        }
+    }
+
+    fn text(app: &PageCollection) -> String {
+        let mut text = String::new();
+        app.get_current_page().visit(&mut |details| {
+            if let Some(text_box) = details.render.as_any().downcast_ref::<TextBox>() {
+                text = text_box.text.clone();
+            }
+            true
+        });
+        text
+    }
+
+    #[test]
+    fn test_focused_component_consumes_events() {
+        let mut app = PageCollection::new(vec![
+            Page::new("Page 1", '1', TextBox::default()),
+            Page::new("Page 2", '2', Static {}),
+        ]);
+        let mut window = Window::new(&app, |ev| ev.as_char() == Some('q'));
+
+        // nothing focused: shortcuts reach the window
+        window.dispatch_event(&InputEvent::Key('1'.into()), &mut app);
+        assert_eq!(text(&app), "");
+
+        window.dispatch_event(&InputEvent::FocusNext, &mut app);
+        for c in ['1', 'q'] {
+            window.dispatch_event(&InputEvent::Key(c.into()), &mut app);
+        }
+        assert_eq!(text(&app), "1q");
+        assert_eq!(app.get_current_page().shortcut, '1');
+        assert!(!window.is_finished());
+
+        // ignored events bubble up to the window
+        window.dispatch_event(&InputEvent::Key('!'.into()), &mut app);
+        window.dispatch_event(&InputEvent::FocusWindow, &mut app);
+        window.dispatch_event(&InputEvent::Key('q'.into()), &mut app);
+        assert!(window.is_finished());
+    }
+
+    #[test]
+    fn test_event_bubbling_order() {
+        let layout_events = Rc::new(RefCell::new(vec![]));
+        let page_events = Rc::new(RefCell::new(vec![]));
+
+        let layout_log = Rc::clone(&layout_events);
+        let mut page = Page::new(
+            "Page 1",
+            '1',
+            row_widget!(TextBox::default(), Static {}).with_event_handler(move |ev, _| {
+                match ev.as_char() {
+                    Some(c @ ('+' | '/')) => {
+                        layout_log.borrow_mut().push(c);
+                        EventResult::Consumed
+                    }
+                    _ => EventResult::Ignored,
+                }
+            }),
+        );
+        let page_log = Rc::clone(&page_events);
+        page.with_menu(Menu::from_entries(vec![(
+            '-',
+            "Minus",
+            move |_: MenuEvent| page_log.borrow_mut().push('-'),
+        )]));
+        let mut app = PageCollection::new(vec![page, Page::new("Page 2", '2', Static {})]);
+        let mut window = Window::new(&app, |_| false);
+        window.dispatch_event(&InputEvent::FocusNext, &mut app);
+
+        for c in ['a', '/', '+', '-', '2'] {
+            window.dispatch_event(&InputEvent::Key(c.into()), &mut app);
+        }
+        assert_eq!(text(&app), "a2");
+        assert_eq!(app.get_current_page().shortcut, '1');
+
+        window.dispatch_event(&InputEvent::FocusWindow, &mut app);
+        window.dispatch_event(&InputEvent::Key('2'.into()), &mut app);
+
+        // the component menu comes before the layout
+        assert!(window.alerts.first_visible().is_some());
+        assert_eq!(*layout_events.borrow(), vec!['+']);
+        assert_eq!(*page_events.borrow(), vec!['-']);
+        assert_eq!(app.get_current_page().shortcut, '2');
+    }
+
+    struct Label {}
+
+    impl FocusableRender for Label {
+        fn render(&mut self, render_props: &RenderProps, buff: &mut Buffer, area: Rect) {
+            buff.set_string(
+                area.x,
+                area.y,
+                format!("focused: {}", render_props.is_focused),
+                Style::default(),
+            );
+        }
+    }
+
+    #[test]
+    fn test_handle_events_from_source() {
+        let mut app = PageCollection::new(vec![Page::new("Page", 'p', Label {})]);
+        let mut window = Window::new(&app, |ev| ev.as_char() == Some('q'));
+        let mut events = ScriptedEventSource::new(vec![
+            InputEvent::FocusNext,
+            InputEvent::FocusWindow,
+            InputEvent::Key('q'.into()),
+        ]);
+        let area = Rect::new(0, 0, 20, 3);
+        let mut buff = Buffer::empty(area);
+
+        let mut frames = vec![];
+        while let Some(event) = events.next_event(Duration::ZERO).unwrap() {
+            window.handle_event(&mut app, event);
+            window.render(&mut app, &mut buff, area);
+            // drawing again doesn't change anything
+            window.render(&mut app, &mut buff, area);
+            frames.push(
+                buff.content[..14]
+                    .iter()
+                    .map(|c| c.symbol())
+                    .collect::<String>(),
+            );
+        }
+
+        assert_eq!(
+            frames,
+            vec!["focused: true ", "focused: false", "focused: false"]
+        );
+        assert!(window.is_finished());
+    }
+
+    #[test]
+    fn test_run_until_end_condition() {
+        let mut app = PageCollection::new(vec![Page::new("Page", 'p', Label {})]);
+        let mut window = Window::new(&app, |ev| ev.as_char() == Some('q'));
+        let mut terminal = Terminal::new(TestBackend::new(20, 3)).unwrap();
+        let mut events = ScriptedEventSource::new(vec![
+            InputEvent::FocusNext,
+            // ignored by the focused label, but only the window ends on it
+            InputEvent::Key('q'.into()),
+            InputEvent::FocusWindow,
+            InputEvent::Key('q'.into()),
+            InputEvent::FocusNext,
+        ]);
+
+        let reason = window
+            .run_with(
+                &mut app,
+                &mut terminal,
+                &mut events,
+                RunOptions::default().with_max_fps(None),
+            )
+            .unwrap();
+
+        assert_eq!(reason, ExitReason::EndCondition);
+        // the last event was never handled
+        let line: String = (0..14)
+            .map(|x| terminal.backend().buffer().get(x, 0).symbol())
+            .collect();
+        assert_eq!(line, "focused: false");
+    }
+
+    #[test]
+    fn test_run_until_source_closed() {
+        let mut app = PageCollection::new(vec![Page::new("Page", 'p', Label {})]);
+        let mut window = Window::new(&app, |_| false);
+        let mut terminal = Terminal::new(TestBackend::new(20, 3)).unwrap();
+        let mut events = ScriptedEventSource::new(vec![InputEvent::Resize(30, 4)]);
+        terminal.backend_mut().resize(30, 4);
+
+        let reason = window
+            .run_with(&mut app, &mut terminal, &mut events, RunOptions::default())
+            .unwrap();
+
+        assert_eq!(reason, ExitReason::SourceClosed);
+        assert_eq!(terminal.size().unwrap(), Rect::new(0, 0, 30, 4));
+    }
+
+    #[derive(Default)]
+    struct RenderCounter {
+        renders: Arc<AtomicUsize>,
+    }
+
+    impl Render for RenderCounter {
+        fn render(&mut self, _render_props: &RenderProps, _buff: &mut Buffer, _area: Rect) {
+            self.renders.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    fn wait_for(renders: &AtomicUsize, count: usize) {
+        wait_until(|| renders.load(Ordering::SeqCst) >= count, TIMEOUT);
+    }
+
+    #[test]
+    fn test_run_draws_only_when_needed() {
+        let counter = RenderCounter::default();
+        let renders = counter.renders.clone();
+        let mut app = PageCollection::new(vec![Page::new("Page", 'p', counter)]);
+        let mut window = Window::new(&app, |ev| ev.as_char() == Some('q'));
+        let mut terminal = Terminal::new(TestBackend::new(20, 3)).unwrap();
+        let (tx, mut events) = ChannelEventSource::channel();
+
+        let handle = window.redraw_handle();
+        let thread_renders = renders.clone();
+        let requester = thread::spawn(move || {
+            wait_for(&thread_renders, 1);
+            handle.request_redraw();
+            wait_for(&thread_renders, 2);
+            tx.send(InputEvent::Key('q'.into())).unwrap();
+        });
+
+        let options = RunOptions::default()
+            .with_tick_rate(None)
+            .with_max_fps(None);
+        let reason = window
+            .run_with(&mut app, &mut terminal, &mut events, options)
+            .unwrap();
+        requester.join().unwrap();
+
+        assert_eq!(reason, ExitReason::EndCondition);
+        // the first frame, the redraw request and the last event
+        assert_eq!(renders.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_run_is_idle_by_default() {
+        let counter = RenderCounter::default();
+        let renders = counter.renders.clone();
+        let mut app = PageCollection::new(vec![Page::new("Page", 'p', counter)]);
+        let mut window = Window::new(&app, |_| false);
+        let mut terminal = Terminal::new(TestBackend::new(20, 3)).unwrap();
+        let (tx, mut events) = ChannelEventSource::channel();
+
+        let thread_renders = renders.clone();
+        let sender = thread::spawn(move || {
+            wait_for(&thread_renders, 1);
+            tx.send(InputEvent::FocusNext).unwrap();
+            wait_for(&thread_renders, 2);
+        });
+        let reason = window
+            .run_with(&mut app, &mut terminal, &mut events, RunOptions::default())
+            .unwrap();
+        sender.join().unwrap();
+
+        assert_eq!(reason, ExitReason::SourceClosed);
+        // one frame to start and one for the event, nothing in between
+        assert_eq!(renders.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_run_limits_frame_rate() {
+        let counter = RenderCounter::default();
+        let renders = counter.renders.clone();
+        let mut app =
+            PageCollection::new(vec![Page::new("Page", 'p', row_widget!(Label {}, counter))]);
+        let mut window = Window::new(&app, |_| false);
+        let mut terminal = Terminal::new(TestBackend::new(40, 3)).unwrap();
+        let mut events = ScriptedEventSource::new(vec![
+            InputEvent::FocusNext,
+            InputEvent::FocusWindow,
+            InputEvent::FocusNext,
+            InputEvent::FocusWindow,
+            InputEvent::FocusNext,
+        ]);
+
+        let options = RunOptions::default()
+            .with_tick_rate(None)
+            .with_max_fps(Some(2));
+        let reason = window
+            .run_with(&mut app, &mut terminal, &mut events, options)
+            .unwrap();
+
+        assert_eq!(reason, ExitReason::SourceClosed);
+        assert!(renders.load(Ordering::SeqCst) < 6);
+        // the frame left out by the limit is drawn before returning
+        let line: String = (0..14)
+            .map(|x| terminal.backend().buffer().get(x, 0).symbol())
+            .collect();
+        assert_eq!(line, "focused: true ");
+    }
+
+    fn focusable_row(count: u16) -> RenderComponent {
+        RenderComponent::row(
+            (0..count)
+                .map(|_| RenderComponent::new_focusable(Focusable {}))
+                .collect(),
+        )
+    }
+
+    fn factory_driver<F: RenderFactory + 'static>(factory: F) -> TestDriver {
+        let app = PageCollection::new(vec![Page::new(
+            "Page",
+            'p',
+            RenderComponent::new_factory(factory),
+        )]);
+        TestDriver::new(app, 30, 5)
+    }
+
+    fn focus_cycle(driver: &mut TestDriver) -> Vec<Option<RenderId>> {
+        let mut focused = vec![];
+        loop {
+            driver.send(InputEvent::FocusNext);
+            focused.push(driver.focused());
+            if driver.focused().is_none() {
+                return focused;
+            }
+        }
+    }
+
+    /// Only rebuilt when invalidated through the handle it shares
+    struct InvalidatedPanels {
+        count: Rc<Cell<u16>>,
+        handle: Rc<RefCell<Option<FactoryHandle>>>,
+    }
+
+    impl RenderFactory for InvalidatedPanels {
+        fn render(&mut self) -> RenderComponent {
+            *self.handle.borrow_mut() = FactoryHandle::current();
+            focusable_row(self.count.get())
+        }
+    }
+
+    #[test]
+    fn test_invalidate_factory() {
+        let count = Rc::new(Cell::new(1));
+        let handle = Rc::new(RefCell::new(None));
+        let mut driver = factory_driver(InvalidatedPanels {
+            count: count.clone(),
+            handle: handle.clone(),
+        });
+        assert_eq!(focus_cycle(&mut driver).len(), 2);
+
+        count.set(3);
+        driver.render();
+        assert_eq!(focus_cycle(&mut driver).len(), 2);
+
+        let handle = handle.borrow().clone().unwrap();
+        handle.invalidate();
+        assert!(handle.is_invalidated());
+        driver.render();
+        assert!(!handle.is_invalidated());
+
+        let focused = focus_cycle(&mut driver);
+        assert_eq!(focused.len(), 4);
+        // the new panels are hit-tested too
+        driver.send(InputEvent::Click(Position::new(25, 2)));
+        assert_eq!(driver.focused(), focused[2]);
+    }
+
+    /// `InvalidatedPanels` with a key on every panel
+    struct InvalidatedKeys(InvalidatedPanels);
+
+    impl RenderFactory for InvalidatedKeys {
+        fn render(&mut self) -> RenderComponent {
+            *self.0.handle.borrow_mut() = FactoryHandle::current();
+            RenderComponent::row(
+                (0..self.0.count.get())
+                    .map(|i| RenderComponent::new_focusable(Focusable {}).with_key(format!("{i}")))
+                    .collect(),
+            )
+        }
+    }
+
+    #[test]
+    fn test_focus_key_added_by_invalidated_factory() {
+        let count = Rc::new(Cell::new(1));
+        let handle = Rc::new(RefCell::new(None));
+        let mut driver = factory_driver(InvalidatedKeys(InvalidatedPanels {
+            count: count.clone(),
+            handle: handle.clone(),
+        }));
+        driver.render();
+        assert!(!driver.focus_key("1"));
+
+        count.set(2);
+        handle.borrow().clone().unwrap().invalidate();
+        // no render in between: the page is rebuilt to find the key
+        assert!(driver.focus_key("1"));
+        assert_eq!(driver.focused(), driver.app().get_current_page().id_of("1"));
+    }
+
+    /// Rebuilt whenever the count changes
+    struct CountedPanels {
+        count: Rc<Cell<u16>>,
+        built: u16,
+    }
+
+    impl RenderFactory for CountedPanels {
+        fn render(&mut self) -> RenderComponent {
+            self.built = self.count.get();
+            focusable_row(self.built)
+        }
+
+        fn should_rebuild(&mut self) -> bool {
+            self.built != self.count.get()
+        }
+    }
+
+    #[test]
+    fn test_factory_rebuilds_on_state_change() {
+        let count = Rc::new(Cell::new(1));
+        let mut driver = factory_driver(CountedPanels {
+            count: count.clone(),
+            built: 0,
+        });
+        driver.send(InputEvent::FocusNext);
+        let focused = driver.focused();
+        assert!(focused.is_some());
+
+        count.set(2);
+        driver.render();
+        // the old panel is gone, and so is its focus
+        assert_eq!(driver.focused(), None);
+        assert_eq!(focus_cycle(&mut driver).len(), 3);
+        assert!(!focus_cycle(&mut driver).contains(&focused));
+    }
+
+    /// Rebuilt whenever the count changes, with a key per panel
+    struct KeyedPanels {
+        count: Rc<Cell<u16>>,
+        built: u16,
+    }
+
+    impl RenderFactory for KeyedPanels {
+        fn render(&mut self) -> RenderComponent {
+            self.built = self.count.get();
+            RenderComponent::row(
+                (0..self.built)
+                    .map(|i| RenderComponent::new_focusable(Focusable {}).with_key(format!("{i}")))
+                    .collect(),
+            )
+        }
+
+        fn should_rebuild(&mut self) -> bool {
+            self.built != self.count.get()
+        }
+    }
+
+    #[test]
+    fn test_rebuild_keeps_keyed_focus() {
+        let count = Rc::new(Cell::new(1));
+        let mut driver = factory_driver(KeyedPanels {
+            count: count.clone(),
+            built: 0,
+        });
+        driver.send(InputEvent::FocusNext);
+        let focused = driver.focused();
+
+        count.set(3);
+        driver.render();
+        assert_eq!(driver.focused(), focused);
+        // the new panels come after the kept one
+        assert_eq!(focus_cycle(&mut driver).len(), 3);
+    }
+
+    #[test]
+    fn test_set_root_keeps_keyed_focus() {
+        let app = PageCollection::new(vec![Page::new(
+            "Page",
+            'p',
+            row_widget!(RenderComponent::new_focusable(Focusable {}).with_key("kept")),
+        )]);
+        let mut driver = TestDriver::new(app, 30, 5);
+        driver.send(InputEvent::FocusNext);
+        let focused = driver.focused();
+
+        driver
+            .app_mut()
+            .page_mut('p')
+            .unwrap()
+            .set_root(row_widget!(
+                Focusable {},
+                RenderComponent::new_focusable(Focusable {}).with_key("kept")
+            ))
+            .unwrap();
+        driver.render();
+        assert_eq!(driver.focused(), focused);
+        driver.send(InputEvent::Click(Position::new(2, 2)));
+        assert_ne!(driver.focused(), focused);
+    }
+
+    #[test]
+    fn test_focus_key() {
+        let app = PageCollection::new(vec![Page::new(
+            "Page",
+            'p',
+            row_widget!(Focusable {} => "first", Focusable {} => "second", Focusable {}),
+        )]);
+        let mut driver = TestDriver::new(app, 30, 5);
+        let second = driver.app().get_current_page().id_of("second");
+
+        assert!(driver.focus_key("second"));
+        assert_eq!(driver.focused(), second);
+        assert!(!driver.focus_key("missing"));
+        assert_eq!(driver.focused(), second);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "use Page::try_new to handle it")]
+    fn test_new_page_asserts_unique_keys() {
+        Page::new(
+            "Page",
+            'p',
+            row_widget!(Focusable {} => "panel", Focusable {} => "panel"),
+        );
+    }
+
+    #[test]
+    fn test_duplicate_keys() {
+        let err = Page::try_new(
+            "Page",
+            'p',
+            row_widget!(Focusable {} => "panel", Focusable {} => "panel"),
+        )
+        .err()
+        .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "the key \"panel\" is used more than once in page \"Page\""
+        );
+
+        let app = PageCollection::new(vec![Page::new_unchecked(
+            "Page",
+            'p',
+            row_widget!(Focusable {} => "panel", Focusable {} => "panel"),
+        )]);
+        assert_eq!(Window::try_new(&app, |_| false).err(), Some(err.clone()));
+
+        let mut page = Page::try_new("Page", 'p', row_widget!(Focusable {} => "panel")).unwrap();
+        let kept = page.id_of("panel");
+        assert_eq!(
+            page.set_root(row_widget!(Focusable {} => "panel", Focusable {} => "panel")),
+            Err(err)
+        );
+        assert_eq!(page.id_of("panel"), kept);
+    }
+
+    #[test]
+    fn test_rebuild_with_duplicate_keys_keeps_previous_tree() {
+        let count = Rc::new(Cell::new(1));
+        let app = PageCollection::new(vec![Page::new(
+            "Page",
+            'p',
+            row_widget!(
+                Focusable {} => "1",
+                RenderComponent::new_factory(KeyedPanels {
+                    count: count.clone(),
+                    built: 0,
+                }),
+            ),
+        )]);
+        let mut driver = TestDriver::new(app, 30, 5);
+        assert_eq!(focus_cycle(&mut driver).len(), 3);
+
+        count.set(2);
+        driver.render();
+        assert_eq!(focus_cycle(&mut driver).len(), 3);
+        let err = driver.app_mut().page_mut('p').unwrap().take_key_error();
+        assert_eq!(err.map(|err| err.key), Some("1".to_string()));
+    }
+
+    /// Adds a panel on Enter, moving the focus to it
+    struct Grow {
+        count: Rc<Cell<u16>>,
+    }
+
+    impl FocusableRender for Grow {
+        fn render(&mut self, _render_props: &RenderProps, _buff: &mut Buffer, _area: Rect) {}
+
+        fn on_event(&mut self, event: &InputEvent, ctx: &mut EventContext) -> EventResult {
+            match event {
+                InputEvent::Key(KeyEvent {
+                    code: KeyCode::Enter,
+                    ..
+                }) => {
+                    ctx.focus.focus_key(self.count.get().to_string());
+                    self.count.set(self.count.get() + 1);
+                    EventResult::Consumed
+                }
+                _ => EventResult::Ignored,
+            }
+        }
+    }
+
+    /// A keyed `Grow` panel per count, rebuilt whenever the count changes
+    struct GrowingPanels {
+        count: Rc<Cell<u16>>,
+        built: u16,
+    }
+
+    impl RenderFactory for GrowingPanels {
+        fn render(&mut self) -> RenderComponent {
+            self.built = self.count.get();
+            RenderComponent::row(
+                (0..self.built)
+                    .map(|i| {
+                        RenderComponent::new_focusable(Grow {
+                            count: self.count.clone(),
+                        })
+                        .with_key(i.to_string())
+                    })
+                    .collect(),
+            )
+        }
+
+        fn should_rebuild(&mut self) -> bool {
+            self.built != self.count.get()
+        }
+    }
+
+    #[test]
+    fn test_focus_component_added_by_handler() {
+        let mut driver = factory_driver(GrowingPanels {
+            count: Rc::new(Cell::new(1)),
+            built: 0,
+        });
+        driver.send(InputEvent::FocusNext);
+        driver.send(InputEvent::Key(KeyCode::Enter.into()));
+
+        let added = driver.current_page().id_of("1");
+        assert!(added.is_some());
+        assert_eq!(driver.focused(), added);
+    }
+
+    /// Asks for the focus of the "target" component at every tick of its timer
+    struct FocusTicker {
+        timer: Timer<ManualClock>,
+    }
+
+    impl Render for FocusTicker {
+        fn render(&mut self, _render_props: &RenderProps, _buff: &mut Buffer, _area: Rect) {
+            self.timer.start();
+        }
+
+        fn on_event(&mut self, event: &InputEvent, ctx: &mut EventContext) -> EventResult {
+            if self.timer.is_tick(event) {
+                ctx.focus.focus_key("target");
+                return EventResult::Consumed;
+            }
+            EventResult::Ignored
+        }
+    }
+
+    fn tick(driver: &mut TestDriver, clock: &ManualClock) {
+        clock.advance(Duration::from_secs(1));
+        wait_until(|| driver.handle_timer_ticks(), TIMEOUT);
+    }
+
+    #[test]
+    fn test_focus_from_ticks_of_current_page_only() {
+        let clock = ManualClock::new();
+        let ticker = FocusTicker {
+            timer: Timer::interval(Duration::from_secs(1)).with_clock(clock.clone()),
+        };
+        let app = PageCollection::new(vec![
+            Page::new("Ticker", 't', row_widget!(ticker, Focusable {} => "target")),
+            Page::new("Other", 'o', row_widget!(Focusable {} => "target")),
+        ]);
+        let mut driver = TestDriver::new(app, 30, 5);
+
+        tick(&mut driver, &clock);
+        assert_eq!(driver.focused(), driver.current_page().id_of("target"));
+
+        driver
+            .send(InputEvent::FocusWindow)
+            .send(InputEvent::Key('o'.into()));
+        assert_eq!(driver.current_page().title(), "Other");
+        tick(&mut driver, &clock);
+        assert_eq!(driver.focused(), None);
+    }
+
+    /// A form field going back to the first field when Enter submits the form
+    struct Field {
+        focused: Rc<Cell<Option<RenderId>>>,
+    }
+
+    impl FocusableRender for Field {
+        fn render(&mut self, _render_props: &RenderProps, _buff: &mut Buffer, _area: Rect) {}
+
+        fn on_event(&mut self, event: &InputEvent, ctx: &mut EventContext) -> EventResult {
+            match event {
+                InputEvent::Key(KeyEvent {
+                    code: KeyCode::Enter,
+                    ..
+                }) => {
+                    self.focused.set(ctx.focus.focused());
+                    ctx.focus.focus_first();
+                    EventResult::Consumed
+                }
+                _ => EventResult::Ignored,
             }
         }
     }
-    None
+
+    #[test]
+    fn test_focus_from_handlers() {
+        let focused = Rc::new(Cell::new(None));
+        let field = || Field {
+            focused: focused.clone(),
+        };
+        let mut page = Page::new(
+            "Form",
+            'f',
+            row_widget!(field() => "name", field() => "email", field() => "submit"),
+        );
+        page.with_menu_entries(vec![('e', "Email", |ev: MenuEvent| {
+            ev.focus.focus_key("email")
+        })]);
+        let mut driver = TestDriver::new(PageCollection::new(vec![page]), 30, 5);
+        let id = |key| driver.current_page().id_of(key);
+        let (name, email, submit) = (id("name"), id("email"), id("submit"));
+
+        driver.send(InputEvent::Key('e'.into()));
+        assert_eq!(driver.focused(), email);
+
+        driver.send(InputEvent::FocusNext);
+        driver.send(InputEvent::Key(KeyCode::Enter.into()));
+        assert_eq!(focused.get(), submit);
+        assert_eq!(driver.focused(), name);
+    }
+
+    #[test]
+    fn test_focus_from_window() {
+        let app = PageCollection::new(vec![
+            Page::new(
+                "Page",
+                'p',
+                row_widget!(Focusable {}, Static {}, Focusable {}),
+            ),
+            Page::new("Empty", 'e', row_widget!(Static {})),
+        ]);
+        let mut driver = TestDriver::new(app, 30, 5);
+        let ids = driver.app().get_focusable_elements();
+
+        assert_eq!(driver.window().focused(), None);
+        assert!(driver.window_mut().focus_last());
+        assert_eq!(driver.window().focused(), Some(ids[1]));
+        assert!(driver.window_mut().focus_first());
+        assert_eq!(driver.window().focused(), Some(ids[0]));
+        assert!(driver.window_mut().focus(&ids[1]));
+        assert!(!driver.window_mut().focus(&RenderId::new()));
+        assert_eq!(driver.window().focused(), Some(ids[1]));
+        driver.window_mut().focus_window();
+        assert_eq!(driver.window().focused(), None);
+
+        driver.send(InputEvent::Key('e'.into()));
+        assert!(!driver.window_mut().focus_first());
+        assert!(!driver.window_mut().focus_last());
+        assert_eq!(driver.window().focused(), None);
+    }
 }
@@ -1,28 +1,23 @@
+// there is no `macros` feature (yet), see `tuiwindow_macros` below
+#![allow(unexpected_cfgs)]
+
 use ratatui::{
     buffer::Buffer,
     layout::Rect,
     widgets::{Block, Borders, Paragraph, Widget},
 };
-use std::error::Error;
-use std::thread;
-use std::time::Duration;
 
 #[cfg(feature = "macros")]
 #[macro_use]
 extern crate tuiwindow_macros;
 
-use tuiwindow::core::RenderComponent;
 #[macro_use]
 extern crate tuiwindow;
 use tuiwindow::{
     core::InputEvent,
-    render::{FocusableRender, Render, RenderProps},
-    tui::TuiCrossterm,
-    windows::{
-        page::Page,
-        page_collection::PageCollection,
-        window::{DefaultEventMapper, Window},
-    },
+    render::{EventContext, EventResult, FocusableRender, Render, RenderProps},
+    testing::{Snapshot, TestDriver},
+    windows::{page::Page, page_collection::PageCollection, window::Window},
 };
 
 struct TestWidget {}
@@ -46,31 +41,55 @@ impl Render for StaticWidget {
     }
 }
 
+struct AlertWidget {}
+
+impl FocusableRender for AlertWidget {
+    fn render(&mut self, _render_props: &RenderProps, buff: &mut Buffer, area: Rect) {
+        Paragraph::new("Press a key").render(area, buff)
+    }
+
+    fn on_event(&mut self, event: &InputEvent, ctx: &mut EventContext) -> EventResult {
+        match event.as_char() {
+            Some(c) => {
+                ctx.alerts.alert(format!("pressed {c}"));
+                EventResult::Consumed
+            }
+            None => EventResult::Ignored,
+        }
+    }
+}
+
 #[test]
-fn tui_test() -> Result<(), Box<dyn Error>> {
-    thread::sleep(Duration::from_millis(500));
-
-    let mut tui = TuiCrossterm::new()?;
-    let terminal = tui.setup()?;
-    // our stuff:
-    let mut app = PageCollection::new(vec![Page::new(
-        "Page",
-        'p',
-        row_widget!(TestWidget {}, StaticWidget::default()),
-    )]);
-    let mut window = Window::new(&app, |ev| match ev {
-        InputEvent::Key(c) => *c == 'q',
-        _ => false,
-    });
-    terminal.draw(|f| {
-        let area = f.size();
-        let buff = f.buffer_mut();
-
-        // draw
-        window.render::<DefaultEventMapper>(&mut app, buff, area)
-    })?;
-    thread::sleep(Duration::from_secs(1));
-
-    // TuiCrossterm::tear_down(terminal)
-    Ok(())
+fn tui_test() {
+    let app = PageCollection::new(vec![
+        Page::new(
+            "Page",
+            'p',
+            row_widget!(TestWidget {}, StaticWidget::default()),
+        ),
+        Page::new("Other", 'o', row_widget!(AlertWidget {})),
+    ]);
+    let window = Window::new(&app, |ev: &InputEvent| ev.as_char() == Some('q'));
+    let mut driver = TestDriver::with_window(app, window, 80, 24);
+
+    assert_eq!(driver.current_page().title(), "Page");
+    assert!(driver.contains_text("Hello world! Focused? false"));
+
+    driver.send(InputEvent::FocusNext);
+    assert!(driver.focused().is_some());
+    assert!(driver.contains_text("Hello world! Focused? true"));
+    driver.assert_snapshot(Snapshot::new("focused_page"));
+
+    driver.send_all([InputEvent::FocusWindow, InputEvent::Key('o'.into())]);
+    assert_eq!(driver.current_page().shortcut(), 'o');
+    assert_eq!(driver.focused(), None);
+    assert!(driver.visible_alert().is_none());
+
+    driver.send(InputEvent::FocusNext).type_text("a");
+    let alert = driver.visible_alert().expect("the widget raised an alert");
+    assert_eq!(alert.message(), "pressed a");
+    assert!(driver.contains_text("pressed a"));
+
+    driver.send(InputEvent::FocusWindow).type_text("q");
+    assert!(driver.is_finished());
 }